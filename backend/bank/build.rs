@@ -1,4 +1,4 @@
-use std::io::Result;
+use std::{env, io::Result, path::PathBuf};
 
 fn main() -> Result<()> {
     let mut protos = vec!["foods.proto"];
@@ -6,7 +6,21 @@ fn main() -> Result<()> {
     #[cfg(feature = "payloads")]
     protos.push("payloads.proto");
 
-    prost_build::compile_protos(&protos, &["../../"])?;
+    let mut config = prost_build::Config::new();
+
+    // JSON mirrors the protobuf field names, missing fields decode as their proto defaults
+    #[cfg(feature = "serde")]
+    config.type_attribute(
+        ".",
+        "#[derive(serde::Serialize, serde::Deserialize)] #[serde(default)]",
+    );
+
+    // read by codegen to emit the frontend's TypeScript
+    config.file_descriptor_set_path(
+        PathBuf::from(env::var("OUT_DIR").unwrap()).join("descriptors.bin"),
+    );
+
+    config.compile_protos(&protos, &["../../"])?;
 
     Ok(())
 }
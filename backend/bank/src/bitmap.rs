@@ -0,0 +1,203 @@
+//! # Food Bitmap
+//!
+//! One bit per food id, the layout every bitmap payload shares: user votes, location bitmaps and search results.
+//!
+//! ## Layout
+//! - Food `id` is bit `id % 8` of byte `id / 8`
+//! - Bits are right to left within a byte, food 0 is the least significant bit of the first byte
+//! - Sized for the bank it was built against, `next_food_id.div_ceil(8)` bytes
+//! - Missing trailing bytes read as 0, so a bitmap from an older, smaller bank still lines up
+//!
+//! ## Example
+//! Foods 0, 3 and 9 set.
+//! ```
+//! # use bank::bitmap::FoodBitmap;
+//! let bitmap: FoodBitmap = [0, 3, 9].into_iter().collect();
+//! assert_eq!(bitmap.as_bytes(), &[0b0000_1001, 0b0000_0010]);
+//! ```
+
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct FoodBitmap {
+    bytes: Vec<u8>,
+}
+
+impl FoodBitmap {
+    // every food of a bank with this next_food_id unset
+    pub fn new(next_food_id: u32) -> Self {
+        Self {
+            bytes: vec![0; Self::byte_len(next_food_id)],
+        }
+    }
+
+    // decodes a proto `bytes` field as is
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    // encodes into a proto `bytes` field
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn byte_len(next_food_id: u32) -> usize {
+        next_food_id.div_ceil(8) as usize
+    }
+
+    pub fn get(&self, id: u32) -> bool {
+        self.bytes
+            .get(id as usize / 8)
+            .is_some_and(|byte| byte >> (id % 8) & 1 == 1)
+    }
+
+    // grows to fit the id when setting
+    pub fn set(&mut self, id: u32, value: bool) {
+        let index = id as usize / 8;
+        let mask = 1 << (id % 8);
+
+        if index >= self.bytes.len() {
+            if !value {
+                return;
+            }
+            self.bytes.resize(index + 1, 0);
+        }
+
+        if value {
+            self.bytes[index] |= mask;
+        } else {
+            self.bytes[index] &= !mask;
+        }
+    }
+
+    // set ids in ascending order
+    pub fn iter_ones(&self) -> impl Iterator<Item = u32> + '_ {
+        ones(&self.bytes)
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.bytes
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum()
+    }
+
+    // (id, set in other) for every id that differs, ascending
+    pub fn diff<'a>(&'a self, other: &'a FoodBitmap) -> impl Iterator<Item = (u32, bool)> + 'a {
+        let length = self.bytes.len().max(other.bytes.len());
+        let byte = |bytes: &[u8], index: usize| bytes.get(index).copied().unwrap_or(0);
+
+        (0..length).flat_map(move |index| {
+            let (old, new) = (byte(&self.bytes, index), byte(&other.bytes, index));
+
+            bits(old ^ new).map(move |bit| ((index * 8 + bit) as u32, new >> bit & 1 == 1))
+        })
+    }
+
+    // fits the bitmap to a bank, bits of ids past it are dropped
+    pub fn resize(&mut self, next_food_id: u32) {
+        self.bytes.resize(Self::byte_len(next_food_id), 0);
+
+        let used = next_food_id % 8;
+        if used != 0
+            && let Some(last) = self.bytes.last_mut()
+        {
+            *last &= (1 << used) - 1;
+        }
+    }
+}
+
+impl FromIterator<u32> for FoodBitmap {
+    fn from_iter<I: IntoIterator<Item = u32>>(ids: I) -> Self {
+        let mut bitmap = FoodBitmap::default();
+        for id in ids {
+            bitmap.set(id, true);
+        }
+
+        bitmap
+    }
+}
+
+fn bits(byte: u8) -> impl Iterator<Item = usize> {
+    (0..8).filter(move |bit| byte >> bit & 1 == 1)
+}
+
+fn ones(bytes: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    bytes
+        .iter()
+        .enumerate()
+        .flat_map(|(index, byte)| bits(*byte).map(move |bit| (index * 8 + bit) as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let bitmap: FoodBitmap = [0, 7, 8].into_iter().collect();
+
+        assert_eq!(bitmap.as_bytes(), &[0b1000_0001, 0b0000_0001]);
+        assert!(bitmap.get(7));
+        assert!(!bitmap.get(6));
+        assert!(!bitmap.get(100));
+    }
+
+    #[test]
+    fn test_set_and_clear() {
+        let mut bitmap = FoodBitmap::new(10);
+        assert_eq!(bitmap.as_bytes(), &[0, 0]);
+
+        bitmap.set(9, true);
+        bitmap.set(9, false);
+        bitmap.set(40, false);
+        assert_eq!(bitmap.as_bytes(), &[0, 0]);
+
+        bitmap.set(20, true);
+        assert_eq!(bitmap.as_bytes(), &[0, 0, 0b0001_0000]);
+    }
+
+    #[test]
+    fn test_iter_ones_and_count() {
+        let bitmap = FoodBitmap::from_bytes(vec![0b0000_0101, 0, 0b1000_0000]);
+
+        assert_eq!(bitmap.iter_ones().collect::<Vec<_>>(), vec![0, 2, 23]);
+        assert_eq!(bitmap.count_ones(), 3);
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = FoodBitmap::from_bytes(vec![0b0000_0011]);
+        let new = FoodBitmap::from_bytes(vec![0b0000_0110, 0b0000_0001]);
+
+        let changes: Vec<(u32, bool)> = old.diff(&new).collect();
+        assert_eq!(changes, vec![(0, false), (2, true), (8, true)]);
+        assert_eq!(old.diff(&old).count(), 0);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut bitmap = FoodBitmap::from_bytes(vec![0xff, 0xff]);
+
+        bitmap.resize(11);
+        assert_eq!(bitmap.as_bytes(), &[0xff, 0b0000_0111]);
+
+        bitmap.resize(4);
+        assert_eq!(bitmap.as_bytes(), &[0b0000_1111]);
+
+        bitmap.resize(24);
+        assert_eq!(bitmap.as_bytes(), &[0b0000_1111, 0, 0]);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let bytes = vec![0b1010_1010, 0b0101_0101];
+
+        assert_eq!(FoodBitmap::from_bytes(bytes.clone()).into_bytes(), bytes);
+        assert_eq!(FoodBitmap::byte_len(0), 0);
+        assert_eq!(FoodBitmap::byte_len(8), 1);
+        assert_eq!(FoodBitmap::byte_len(9), 2);
+    }
+}
@@ -2,10 +2,15 @@ use std::fs;
 
 use prost::Message;
 
+pub mod bitmap;
+
 pub mod foods {
     include!(concat!(env!("OUT_DIR"), "/foods.rs"));
 }
 
+// encoded prost_types::FileDescriptorSet of every compiled proto
+pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/descriptors.bin"));
+
 #[cfg(feature = "payloads")]
 mod payloads_lib {
     pub mod payloads {
@@ -1,4 +1,6 @@
-use anyhow::Error;
+use std::{collections::HashMap, time::SystemTime};
+
+use anyhow::{Error, anyhow};
 use prost::{DecodeError, Message, bytes::Buf};
 use reqwest::get;
 
@@ -9,33 +11,122 @@ const REMOTE_BANK_PATH: &str = "https://github.com/dadal00/food/raw/refs/heads/m
 pub struct RemoteBank {
     pub bank: Bank,
     pub food_id_to_name: Vec<String>,
+    // collided ids from before the keys were sanitized, votes on them count for the kept food
+    pub alias_to_kept: HashMap<u32, u32>,
     pub location_id_to_name: Vec<String>,
+    pub fetched_at: SystemTime,
+}
+
+impl RemoteBank {
+    // indexes the bank by id, alias ids stay unnamed, fetched_at is now
+    pub fn new(bank: Bank) -> Result<Self, Error> {
+        let mut food_id_to_name: Vec<String> =
+            vec!["".to_string(); (bank.next_food_id) as usize];
+        for (key, value) in bank.foods.clone() {
+            let name = food_id_to_name.get_mut(value.id as usize).ok_or_else(|| {
+                anyhow!("food {key} has id {} past next_food_id {}", value.id, bank.next_food_id)
+            })?;
+            *name = key.clone();
+        }
+
+        let mut alias_to_kept = HashMap::new();
+        for (alias_id, kept_id) in &bank.food_aliases {
+            if food_id_to_name.get(*alias_id as usize).is_none() {
+                return Err(anyhow!(
+                    "alias {alias_id} is past next_food_id {}",
+                    bank.next_food_id
+                ));
+            }
+            if food_id_to_name
+                .get(*kept_id as usize)
+                .is_none_or(String::is_empty)
+            {
+                return Err(anyhow!("alias {alias_id} points at unknown food {kept_id}"));
+            }
+            alias_to_kept.insert(*alias_id, *kept_id);
+        }
+
+        let mut location_id_to_name: Vec<String> =
+            vec!["".to_string(); (bank.next_location_id) as usize];
+        for (key, value) in bank.locations.clone() {
+            let name = location_id_to_name.get_mut(value as usize).ok_or_else(|| {
+                anyhow!("location {key} has id {value} past next_location_id {}", bank.next_location_id)
+            })?;
+            *name = key.clone();
+        }
+
+        Ok(RemoteBank {
+            bank,
+            food_id_to_name,
+            alias_to_kept,
+            location_id_to_name,
+            fetched_at: SystemTime::now(),
+        })
+    }
 }
 
 pub async fn get_remote_bank() -> Result<RemoteBank, Error> {
     let response = get(REMOTE_BANK_PATH).await?;
     let bytes = response.bytes().await?;
 
-    let bank = Bank::decode(&*bytes)?;
+    RemoteBank::new(Bank::decode(&*bytes)?)
+}
+
+pub fn get_votes_from_bytes<B: Buf>(buf: B) -> Result<Votes, DecodeError> {
+    Votes::decode(buf)
+}
+
+pub fn encode_payload<M: Message>(payload: &M) -> Vec<u8> {
+    payload.encode_to_vec()
+}
+
+pub fn decode_payload<M: Message + Default, B: Buf>(buf: B) -> Result<M, DecodeError> {
+    M::decode(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::foods::Food;
 
-    let mut food_id_to_name: Vec<String> = vec!["".to_string(); (bank.next_food_id) as usize];
-    for (key, value) in bank.foods.clone() {
-        food_id_to_name[value.id as usize] = key.clone();
+    // "Fried_Rice" id 7 collided with "Fried Rice!" id 3 when the keys were sanitized
+    fn collided_bank() -> Bank {
+        Bank {
+            next_food_id: 8,
+            foods: HashMap::from([
+                ("Fried Rice".to_string(), Food { id: 3, ..Default::default() }),
+                ("Pho".to_string(), Food { id: 5, ..Default::default() }),
+            ]),
+            food_aliases: HashMap::from([(7, 3)]),
+            ..Default::default()
+        }
     }
 
-    let mut location_id_to_name: Vec<String> =
-        vec!["".to_string(); (bank.next_location_id) as usize];
-    for (key, value) in bank.locations.clone() {
-        location_id_to_name[value as usize] = key.clone();
+    #[test]
+    fn test_alias_ids_point_at_the_kept_id() {
+        let remote_bank = RemoteBank::new(collided_bank()).unwrap();
+
+        assert_eq!(remote_bank.food_id_to_name[3], "Fried Rice");
+        assert_eq!(remote_bank.food_id_to_name[7], "");
+        assert_eq!(remote_bank.food_id_to_name[5], "Pho");
+        assert_eq!(remote_bank.food_id_to_name[4], "");
+        assert_eq!(remote_bank.alias_to_kept, HashMap::from([(7, 3)]));
     }
 
-    Ok(RemoteBank {
-        bank,
-        food_id_to_name,
-        location_id_to_name,
-    })
-}
+    #[test]
+    fn test_ids_past_the_bank_are_rejected() {
+        let mut bank = collided_bank();
+        bank.food_aliases.insert(8, 3);
+        assert!(RemoteBank::new(bank).is_err());
 
-pub fn get_votes_from_bytes<B: Buf>(buf: B) -> Result<Votes, DecodeError> {
-    Votes::decode(buf)
+        let mut bank = collided_bank();
+        bank.food_aliases.insert(6, 9);
+        assert!(RemoteBank::new(bank).is_err());
+
+        let mut bank = collided_bank();
+        bank.foods.insert("Ramen".to_string(), Food { id: 8, ..Default::default() });
+        assert!(RemoteBank::new(bank).is_err());
+    }
 }
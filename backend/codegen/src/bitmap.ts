@@ -0,0 +1,97 @@
+// One bit per food id, the layout of every bitmap payload: user votes, location bitmaps and search results.
+// Food `id` is bit `id % 8` of byte `id / 8`, food 0 is the least significant bit of the first byte.
+// Sized for the bank it was built against, missing trailing bytes read as 0.
+
+export const BITS_PER_BYTE = 8;
+
+// bytes of a bitmap for a bank with this next_food_id
+export function byteLen(nextFoodId: number): number {
+  return Math.ceil(nextFoodId / BITS_PER_BYTE);
+}
+
+export class FoodBitmap {
+  constructor(public bytes: Uint8Array = new Uint8Array()) {}
+
+  // every food of a bank with this next_food_id unset
+  static empty(nextFoodId: number): FoodBitmap {
+    return new FoodBitmap(new Uint8Array(byteLen(nextFoodId)));
+  }
+
+  static of(ids: Iterable<number>): FoodBitmap {
+    const bitmap = new FoodBitmap();
+    for (const id of ids) {
+      bitmap.set(id, true);
+    }
+    return bitmap;
+  }
+
+  get(id: number): boolean {
+    const byte = this.bytes[Math.floor(id / BITS_PER_BYTE)] ?? 0;
+    return ((byte >> id % BITS_PER_BYTE) & 1) === 1;
+  }
+
+  // grows to fit the id when setting
+  set(id: number, value: boolean): void {
+    const index = Math.floor(id / BITS_PER_BYTE);
+    const mask = 1 << id % BITS_PER_BYTE;
+
+    if (index >= this.bytes.length) {
+      if (!value) {
+        return;
+      }
+      this.bytes = grown(this.bytes, index + 1);
+    }
+
+    if (value) {
+      this.bytes[index] |= mask;
+    } else {
+      this.bytes[index] &= ~mask;
+    }
+  }
+
+  // set ids in ascending order
+  *ones(): Generator<number> {
+    for (let id = 0; id < this.bytes.length * BITS_PER_BYTE; id++) {
+      if (this.get(id)) {
+        yield id;
+      }
+    }
+  }
+
+  countOnes(): number {
+    let count = 0;
+    for (const _ of this.ones()) {
+      count++;
+    }
+    return count;
+  }
+
+  // [id, set in other] for every id that differs, ascending
+  *diff(other: FoodBitmap): Generator<[number, boolean]> {
+    const length = Math.max(this.bytes.length, other.bytes.length) * BITS_PER_BYTE;
+    for (let id = 0; id < length; id++) {
+      if (this.get(id) !== other.get(id)) {
+        yield [id, other.get(id)];
+      }
+    }
+  }
+
+  // fits the bitmap to a bank, bits of ids past it are dropped
+  resize(nextFoodId: number): void {
+    this.bytes = grown(this.bytes.slice(0, byteLen(nextFoodId)), byteLen(nextFoodId));
+
+    const used = nextFoodId % BITS_PER_BYTE;
+    if (used !== 0 && this.bytes.length > 0) {
+      this.bytes[this.bytes.length - 1] &= (1 << used) - 1;
+    }
+  }
+}
+
+function grown(bytes: Uint8Array, length: number): Uint8Array {
+  if (bytes.length >= length) {
+    return bytes;
+  }
+  const copy = new Uint8Array(length);
+  copy.set(bytes);
+  return copy;
+}
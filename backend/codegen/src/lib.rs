@@ -0,0 +1,119 @@
+//! # Codegen
+//!
+//! The frontend contract, generated from the same protos and routes as the Rust types.
+//!
+//! ## Output
+//! - `protobuf.ts`: wire format runtime, no dependencies
+//! - `bitmap.ts`: the food bitmap layout and helpers of [`bank::bitmap`], with an example encoded by the Rust side
+//! - `foods.ts`, `payloads.ts`: types and encode/decode helpers, see [`typescript`]
+//! - `routes.json`, `routes.ts`: [`server::manifest::ROUTES`]
+//!
+//! ## Notes
+//! - Committed under `schema/`, the frontend copies or imports it from there
+//! - `tests/schema.rs` fails when the committed files are out of date
+//!
+//! ## Commands
+//!
+//! Regenerate after changing a proto or a route.
+//! ```sh
+//! cargo codegen
+//! ```
+//!
+//! Write somewhere else, e.g. the frontend.
+//! ```sh
+//! cargo codegen ../frontend/src/lib/schema
+//! ```
+//!
+//! Check without writing.
+//! ```sh
+//! cargo codegen --check
+//! ```
+use std::{fs, io, path::Path};
+
+use bank::bitmap::FoodBitmap;
+use prost::Message;
+use prost_types::FileDescriptorSet;
+use server::manifest::ROUTES;
+
+pub mod typescript;
+
+pub const SCHEMA_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../schema");
+
+const RUNTIME: &str = include_str!("protobuf.ts");
+const BITMAP: &str = include_str!("bitmap.ts");
+// ids the layout example sets, across a byte boundary
+const BITMAP_EXAMPLE: [u32; 3] = [0, 3, 9];
+
+const ROUTE_TYPES: &str = r#"export type Body = { message: string } | { json: string } | "text";
+
+export interface Route {
+  method: "GET" | "POST";
+  path: string;
+  auth: "token" | "admin" | "none";
+  query: string[];
+  request: Body | null;
+  response: Body;
+  summary: string;
+}
+"#;
+
+pub fn descriptors() -> FileDescriptorSet {
+    FileDescriptorSet::decode(bank::FILE_DESCRIPTOR_SET)
+        .expect("bank embeds a valid descriptor set")
+}
+
+// (file name, contents) of every generated file
+pub fn render() -> Vec<(String, String)> {
+    let mut files = vec![(
+        "protobuf.ts".to_string(),
+        format!("// Generated by `cargo codegen`, do not edit.\n\n{RUNTIME}"),
+    )];
+    files.push(("bitmap.ts".to_string(), render_bitmap()));
+    files.extend(typescript::render(&descriptors()));
+
+    let routes = serde_json::to_string_pretty(ROUTES).expect("routes serialize");
+    files.push(("routes.json".to_string(), format!("{routes}\n")));
+    files.push((
+        "routes.ts".to_string(),
+        format!(
+            "// Generated by `cargo codegen` from server::manifest, do not edit.\n\n{ROUTE_TYPES}\nexport const routes: Route[] = {routes};\n"
+        ),
+    ));
+
+    files
+}
+
+// the helpers plus bytes FoodBitmap encodes, so frontend tests check against Rust rather than a copy
+fn render_bitmap() -> String {
+    let bitmap: FoodBitmap = BITMAP_EXAMPLE.into_iter().collect();
+    let bytes: Vec<String> = bitmap.as_bytes().iter().map(u8::to_string).collect();
+    let ids: Vec<String> = BITMAP_EXAMPLE.iter().map(u32::to_string).collect();
+
+    format!(
+        "// Generated by `cargo codegen` from bank::bitmap, do not edit.\n\n{BITMAP}\n\
+         // encoded by bank::bitmap::FoodBitmap\n\
+         export const LAYOUT_EXAMPLE = {{ ids: [{}], bytes: [{}] }};\n",
+        ids.join(", "),
+        bytes.join(", ")
+    )
+}
+
+pub fn write(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (name, contents) in render() {
+        fs::write(dir.join(name), contents)?;
+    }
+
+    Ok(())
+}
+
+// generated files that are missing or differ from what is on disk
+pub fn stale(dir: &Path) -> Vec<String> {
+    render()
+        .into_iter()
+        .filter(|(name, contents)| {
+            fs::read_to_string(dir.join(name)).ok().as_ref() != Some(contents)
+        })
+        .map(|(name, _)| name)
+        .collect()
+}
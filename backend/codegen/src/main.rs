@@ -0,0 +1,35 @@
+use std::{path::PathBuf, process::exit};
+
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Args {
+    // defaults to the schema directory at the repository root
+    out: Option<PathBuf>,
+
+    // fails instead of writing when a file is out of date
+    #[arg(long)]
+    check: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    let out = args
+        .out
+        .unwrap_or_else(|| PathBuf::from(codegen::SCHEMA_DIR));
+
+    if args.check {
+        let stale = codegen::stale(&out);
+        if !stale.is_empty() {
+            eprintln!("Out of date, run `cargo codegen`: {}", stale.join(", "));
+            exit(1);
+        }
+        return;
+    }
+
+    if let Err(e) = codegen::write(&out) {
+        eprintln!("Failed to write {}: {e}", out.display());
+        exit(1);
+    }
+}
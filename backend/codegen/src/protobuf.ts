@@ -0,0 +1,284 @@
+// Minimal protobuf wire format runtime for the generated messages, no dependencies.
+// 64 bit integers are numbers, values past 2^53 throw a RangeError rather than lose precision.
+
+export enum WireType {
+  Varint = 0,
+  Fixed64 = 1,
+  LengthDelimited = 2,
+  Fixed32 = 5,
+}
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+const MAX_SAFE = BigInt(Number.MAX_SAFE_INTEGER);
+
+function toBig(value: number): bigint {
+  if (!Number.isSafeInteger(value)) {
+    throw new RangeError(`${value} is not a 64 bit integer a number can hold`);
+  }
+  return BigInt(value);
+}
+
+function toNumber(value: bigint): number {
+  if (value > MAX_SAFE || value < -MAX_SAFE) {
+    throw new RangeError(`${value} is past 2^53, a number cannot hold it`);
+  }
+  return Number(value);
+}
+
+export class Writer {
+  private buf: number[] = [];
+  private stack: number[][] = [];
+
+  tag(field: number, wireType: WireType): this {
+    return this.uint32(((field << 3) | wireType) >>> 0);
+  }
+
+  uint32(value: number): this {
+    value >>>= 0;
+    while (value > 0x7f) {
+      this.buf.push((value & 0x7f) | 0x80);
+      value >>>= 7;
+    }
+    this.buf.push(value);
+    return this;
+  }
+
+  // negative values take 10 bytes, as in every protobuf implementation
+  int32(value: number): this {
+    return value < 0 ? this.int64(value) : this.uint32(value);
+  }
+
+  sint32(value: number): this {
+    return this.uint32((value << 1) ^ (value >> 31));
+  }
+
+  int64(value: number): this {
+    return this.varint64(BigInt.asUintN(64, toBig(value)));
+  }
+
+  uint64(value: number): this {
+    return this.int64(value);
+  }
+
+  sint64(value: number): this {
+    const big = toBig(value);
+    return this.varint64(BigInt.asUintN(64, (big << 1n) ^ (big >> 63n)));
+  }
+
+  bool(value: boolean): this {
+    return this.uint32(value ? 1 : 0);
+  }
+
+  fixed32(value: number): this {
+    return this.little(4, (view) => view.setUint32(0, value, true));
+  }
+
+  sfixed32(value: number): this {
+    return this.little(4, (view) => view.setInt32(0, value, true));
+  }
+
+  fixed64(value: number): this {
+    return this.little(8, (view) => view.setBigUint64(0, BigInt.asUintN(64, toBig(value)), true));
+  }
+
+  sfixed64(value: number): this {
+    return this.little(8, (view) => view.setBigInt64(0, BigInt.asIntN(64, toBig(value)), true));
+  }
+
+  float(value: number): this {
+    return this.little(4, (view) => view.setFloat32(0, value, true));
+  }
+
+  double(value: number): this {
+    return this.little(8, (view) => view.setFloat64(0, value, true));
+  }
+
+  bytes(value: Uint8Array): this {
+    this.uint32(value.length);
+    for (const byte of value) {
+      this.buf.push(byte);
+    }
+    return this;
+  }
+
+  string(value: string): this {
+    return this.bytes(encoder.encode(value));
+  }
+
+  // starts a length delimited message, closed by ldelim
+  fork(): this {
+    this.stack.push(this.buf);
+    this.buf = [];
+    return this;
+  }
+
+  ldelim(): this {
+    const inner = this.buf;
+    const outer = this.stack.pop();
+    if (outer === undefined) {
+      throw new Error("ldelim without fork");
+    }
+    this.buf = outer;
+    this.uint32(inner.length);
+    for (const byte of inner) {
+      this.buf.push(byte);
+    }
+    return this;
+  }
+
+  finish(): Uint8Array {
+    return Uint8Array.from(this.buf);
+  }
+
+  private varint64(value: bigint): this {
+    while (value > 0x7fn) {
+      this.buf.push(Number(value & 0x7fn) | 0x80);
+      value >>= 7n;
+    }
+    this.buf.push(Number(value));
+    return this;
+  }
+
+  private little(length: number, write: (view: DataView) => void): this {
+    const view = new DataView(new ArrayBuffer(length));
+    write(view);
+    for (let index = 0; index < length; index++) {
+      this.buf.push(view.getUint8(index));
+    }
+    return this;
+  }
+}
+
+export class Reader {
+  pos = 0;
+  private view: DataView;
+
+  constructor(readonly buf: Uint8Array) {
+    this.view = new DataView(buf.buffer, buf.byteOffset, buf.byteLength);
+  }
+
+  static from(input: Uint8Array | Reader): Reader {
+    return input instanceof Reader ? input : new Reader(input);
+  }
+
+  // end position of a message, the rest of the buffer when the length is unknown
+  end(length?: number): number {
+    const end = length === undefined ? this.buf.length : this.pos + length;
+    if (end > this.buf.length) {
+      throw new RangeError("message past the end of the buffer");
+    }
+    return end;
+  }
+
+  tag(): [number, WireType] {
+    const tag = this.uint32();
+    return [tag >>> 3, (tag & 7) as WireType];
+  }
+
+  uint32(): number {
+    return Number(BigInt.asUintN(32, this.varint64()));
+  }
+
+  int32(): number {
+    return Number(BigInt.asIntN(32, this.varint64()));
+  }
+
+  sint32(): number {
+    const value = this.uint32();
+    return (value >>> 1) ^ -(value & 1);
+  }
+
+  uint64(): number {
+    return toNumber(BigInt.asUintN(64, this.varint64()));
+  }
+
+  int64(): number {
+    return toNumber(BigInt.asIntN(64, this.varint64()));
+  }
+
+  sint64(): number {
+    const value = this.varint64();
+    return toNumber(BigInt.asIntN(64, (value >> 1n) ^ -(value & 1n)));
+  }
+
+  bool(): boolean {
+    return this.varint64() !== 0n;
+  }
+
+  fixed32(): number {
+    return this.view.getUint32(this.advance(4), true);
+  }
+
+  sfixed32(): number {
+    return this.view.getInt32(this.advance(4), true);
+  }
+
+  fixed64(): number {
+    return toNumber(this.view.getBigUint64(this.advance(8), true));
+  }
+
+  sfixed64(): number {
+    return toNumber(this.view.getBigInt64(this.advance(8), true));
+  }
+
+  float(): number {
+    return this.view.getFloat32(this.advance(4), true);
+  }
+
+  double(): number {
+    return this.view.getFloat64(this.advance(8), true);
+  }
+
+  bytes(): Uint8Array {
+    const length = this.uint32();
+    const start = this.advance(length);
+    return this.buf.slice(start, start + length);
+  }
+
+  string(): string {
+    return decoder.decode(this.bytes());
+  }
+
+  skip(wireType: WireType): void {
+    switch (wireType) {
+      case WireType.Varint:
+        this.varint64();
+        break;
+      case WireType.Fixed64:
+        this.advance(8);
+        break;
+      case WireType.LengthDelimited:
+        this.advance(this.uint32());
+        break;
+      case WireType.Fixed32:
+        this.advance(4);
+        break;
+      default:
+        throw new Error(`unsupported wire type ${wireType}`);
+    }
+  }
+
+  private varint64(): bigint {
+    let result = 0n;
+    for (let shift = 0n; shift < 70n; shift += 7n) {
+      const byte = this.buf[this.advance(1)];
+      result |= BigInt(byte & 0x7f) << shift;
+      if ((byte & 0x80) === 0) {
+        return result;
+      }
+    }
+    throw new Error("varint longer than 10 bytes");
+  }
+
+  // moves past length bytes, returns where they start
+  private advance(length: number): number {
+    const start = this.pos;
+    if (start + length > this.buf.length) {
+      throw new RangeError("read past the end of the buffer");
+    }
+    this.pos += length;
+    return start;
+  }
+}
@@ -0,0 +1,487 @@
+//! # TypeScript
+//!
+//! One module per proto file, named after it, next to the `protobuf.ts` wire format runtime.
+//!
+//! ## Output
+//! - An `interface` and a same named `const` with `create`, `encode` and `decode` per message
+//! - An `enum` per proto enum
+//! - Nested messages and enums are `Outer_Inner`
+//! - Field names stay snake_case, the same keys the server's JSON uses
+//!
+//! ## Types
+//! - 32 bit and 64 bit integers, floats and doubles are `number`, 64 bit values past 2^53 throw a `RangeError`
+//! - `bytes` is `Uint8Array`, the server's JSON sends them as arrays of numbers
+//! - Maps are plain objects, singular messages and `optional` fields may be missing or `null`
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+};
+
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+    FileDescriptorSet,
+    field_descriptor_proto::{Label, Type},
+};
+
+const HEADER: &str = "// Generated by `cargo codegen`";
+
+// where a fully qualified proto name ended up
+struct Named {
+    module: String,
+    ident: String,
+}
+
+struct Index<'a> {
+    names: HashMap<String, Named>,
+    // map entry message to its key and value fields
+    maps: HashMap<String, (&'a FieldDescriptorProto, &'a FieldDescriptorProto)>,
+}
+
+// (file name, contents) for every proto file in the set
+pub fn render(set: &FileDescriptorSet) -> Vec<(String, String)> {
+    let mut index = Index {
+        names: HashMap::new(),
+        maps: HashMap::new(),
+    };
+    for file in &set.file {
+        let prefix = format!(".{}", file.package());
+        index_messages(&mut index, &module(file), &prefix, "", &file.message_type);
+        index_enums(&mut index, &module(file), &prefix, "", &file.enum_type);
+    }
+
+    set.file
+        .iter()
+        .map(|file| (format!("{}.ts", module(file)), render_file(&index, file)))
+        .collect()
+}
+
+fn module(file: &FileDescriptorProto) -> String {
+    file.name().trim_end_matches(".proto").to_string()
+}
+
+fn index_messages<'a>(
+    index: &mut Index<'a>,
+    module: &str,
+    prefix: &str,
+    outer: &str,
+    messages: &'a [DescriptorProto],
+) {
+    for message in messages {
+        let name = format!("{prefix}.{}", message.name());
+        let ident = format!("{outer}{}", message.name());
+
+        if message
+            .options
+            .as_ref()
+            .is_some_and(|options| options.map_entry())
+        {
+            index
+                .maps
+                .insert(name, (&message.field[0], &message.field[1]));
+            continue;
+        }
+
+        let nested = format!("{ident}_");
+        index_messages(index, module, &name, &nested, &message.nested_type);
+        index_enums(index, module, &name, &nested, &message.enum_type);
+        index.names.insert(
+            name,
+            Named {
+                module: module.to_string(),
+                ident,
+            },
+        );
+    }
+}
+
+fn index_enums(
+    index: &mut Index,
+    module: &str,
+    prefix: &str,
+    outer: &str,
+    enums: &[EnumDescriptorProto],
+) {
+    for proto_enum in enums {
+        index.names.insert(
+            format!("{prefix}.{}", proto_enum.name()),
+            Named {
+                module: module.to_string(),
+                ident: format!("{outer}{}", proto_enum.name()),
+            },
+        );
+    }
+}
+
+fn render_file(index: &Index, file: &FileDescriptorProto) -> String {
+    let module = module(file);
+    let mut out = format!("{HEADER} from {}, do not edit.\n\n", file.name());
+
+    let imports: BTreeSet<(&str, &str)> = index
+        .names
+        .iter()
+        .filter(|(name, named)| named.module != module && references(index, file, name))
+        .map(|(_, named)| (named.module.as_str(), named.ident.as_str()))
+        .collect();
+
+    out.push_str("import { Reader, WireType, Writer } from \"./protobuf\";\n");
+    let mut modules: Vec<&str> = imports.iter().map(|(module, _)| *module).collect();
+    modules.dedup();
+    for other in modules {
+        let idents: Vec<&str> = imports
+            .iter()
+            .filter(|(module, _)| *module == other)
+            .map(|(_, ident)| *ident)
+            .collect();
+        let _ = writeln!(
+            out,
+            "import {{ {} }} from \"./{other}\";",
+            idents.join(", ")
+        );
+    }
+
+    render_enums(&mut out, "", &file.enum_type);
+    render_messages(&mut out, index, "", &file.message_type);
+
+    out
+}
+
+// whether any field of the file has this fully qualified type
+fn references(index: &Index, file: &FileDescriptorProto, name: &str) -> bool {
+    fn walk(index: &Index, messages: &[DescriptorProto], name: &str) -> bool {
+        messages.iter().any(|message| {
+            message.field.iter().any(|field| {
+                field.type_name() == name
+                    || index
+                        .maps
+                        .get(field.type_name())
+                        .is_some_and(|(_, value)| value.type_name() == name)
+            }) || walk(index, &message.nested_type, name)
+        })
+    }
+
+    walk(index, &file.message_type, name)
+}
+
+fn render_enums(out: &mut String, outer: &str, enums: &[EnumDescriptorProto]) {
+    for proto_enum in enums {
+        let _ = writeln!(out, "\nexport enum {outer}{} {{", proto_enum.name());
+        for value in &proto_enum.value {
+            let _ = writeln!(out, "  {} = {},", value.name(), value.number());
+        }
+        out.push_str("}\n");
+    }
+}
+
+fn render_messages(out: &mut String, index: &Index, outer: &str, messages: &[DescriptorProto]) {
+    for message in messages {
+        if message
+            .options
+            .as_ref()
+            .is_some_and(|options| options.map_entry())
+        {
+            continue;
+        }
+
+        let ident = format!("{outer}{}", message.name());
+        let nested = format!("{ident}_");
+        render_enums(out, &nested, &message.enum_type);
+        render_messages(out, index, &nested, &message.nested_type);
+
+        render_interface(out, index, &ident, message);
+        render_codec(out, index, &ident, message);
+    }
+}
+
+fn render_interface(out: &mut String, index: &Index, ident: &str, message: &DescriptorProto) {
+    let _ = writeln!(out, "\nexport interface {ident} {{");
+    for field in &message.field {
+        let (optional, nullable) = if optional(field) {
+            ("?", " | null")
+        } else {
+            ("", "")
+        };
+        let _ = writeln!(
+            out,
+            "  {}{optional}: {}{nullable};",
+            field.name(),
+            field_type(index, field)
+        );
+    }
+    out.push_str("}\n");
+}
+
+fn render_codec(out: &mut String, index: &Index, ident: &str, message: &DescriptorProto) {
+    let _ = writeln!(out, "\nexport const {ident} = {{");
+
+    let _ = writeln!(out, "  create(fields: Partial<{ident}> = {{}}): {ident} {{");
+    out.push_str("    return {\n");
+    for field in message.field.iter().filter(|field| !optional(field)) {
+        let _ = writeln!(out, "      {}: {},", field.name(), default(index, field));
+    }
+    out.push_str("      ...fields,\n    };\n  },\n\n");
+
+    let _ = writeln!(
+        out,
+        "  encode(message: {ident}, writer: Writer = new Writer()): Writer {{"
+    );
+    for field in &message.field {
+        encode_field(out, index, field);
+    }
+    out.push_str("    return writer;\n  },\n\n");
+
+    let _ = writeln!(
+        out,
+        "  decode(input: Uint8Array | Reader, length?: number): {ident} {{"
+    );
+    out.push_str("    const reader = Reader.from(input);\n");
+    out.push_str("    const end = reader.end(length);\n");
+    let _ = writeln!(out, "    const message = {ident}.create();");
+    out.push_str("    while (reader.pos < end) {\n");
+    out.push_str("      const [field, wireType] = reader.tag();\n");
+    out.push_str("      switch (field) {\n");
+    for field in &message.field {
+        decode_field(out, index, field);
+    }
+    out.push_str("        default:\n          reader.skip(wireType);\n      }\n    }\n");
+    out.push_str("    return message;\n  },\n};\n");
+}
+
+fn optional(field: &FieldDescriptorProto) -> bool {
+    field.label() != Label::Repeated && (field.r#type() == Type::Message || field.proto3_optional())
+}
+
+fn named<'a>(index: &'a Index, field: &FieldDescriptorProto) -> &'a str {
+    match index.names.get(field.type_name()) {
+        Some(named) => &named.ident,
+        None => panic!("unknown type {}", field.type_name()),
+    }
+}
+
+// TypeScript type, reader and writer method, wire type and zero value of a singular field
+fn scalar(
+    field: &FieldDescriptorProto,
+) -> (&'static str, &'static str, &'static str, &'static str) {
+    match field.r#type() {
+        Type::Double => ("number", "double", "Fixed64", "0"),
+        Type::Float => ("number", "float", "Fixed32", "0"),
+        Type::Int64 => ("number", "int64", "Varint", "0"),
+        Type::Uint64 => ("number", "uint64", "Varint", "0"),
+        Type::Int32 => ("number", "int32", "Varint", "0"),
+        Type::Fixed64 => ("number", "fixed64", "Fixed64", "0"),
+        Type::Fixed32 => ("number", "fixed32", "Fixed32", "0"),
+        Type::Bool => ("boolean", "bool", "Varint", "false"),
+        Type::String => ("string", "string", "LengthDelimited", "\"\""),
+        Type::Bytes => ("Uint8Array", "bytes", "LengthDelimited", "new Uint8Array()"),
+        Type::Uint32 => ("number", "uint32", "Varint", "0"),
+        Type::Enum => ("", "int32", "Varint", "0"),
+        Type::Sfixed32 => ("number", "sfixed32", "Fixed32", "0"),
+        Type::Sfixed64 => ("number", "sfixed64", "Fixed64", "0"),
+        Type::Sint32 => ("number", "sint32", "Varint", "0"),
+        Type::Sint64 => ("number", "sint64", "Varint", "0"),
+        Type::Message => ("", "", "LengthDelimited", ""),
+        Type::Group => panic!("groups are not supported, field {}", field.name()),
+    }
+}
+
+fn value_type(index: &Index, field: &FieldDescriptorProto) -> String {
+    match field.r#type() {
+        Type::Message | Type::Enum => named(index, field).to_string(),
+        _ => scalar(field).0.to_string(),
+    }
+}
+
+fn map_entry<'a>(
+    index: &'a Index,
+    field: &FieldDescriptorProto,
+) -> Option<&'a (&'a FieldDescriptorProto, &'a FieldDescriptorProto)> {
+    index.maps.get(field.type_name())
+}
+
+fn field_type(index: &Index, field: &FieldDescriptorProto) -> String {
+    if let Some((key, value)) = map_entry(index, field) {
+        let key = match key.r#type() {
+            Type::String => "string",
+            Type::Bool => panic!("bool map keys are not supported, field {}", field.name()),
+            _ => "number",
+        };
+        return format!("{{ [key: {key}]: {} }}", value_type(index, value));
+    }
+
+    let value = value_type(index, field);
+    if field.label() == Label::Repeated {
+        format!("{value}[]")
+    } else {
+        value
+    }
+}
+
+fn default(index: &Index, field: &FieldDescriptorProto) -> String {
+    if map_entry(index, field).is_some() {
+        "{}".to_string()
+    } else if field.label() == Label::Repeated {
+        "[]".to_string()
+    } else if field.r#type() == Type::Message {
+        format!("{}.create()", named(index, field))
+    } else {
+        scalar(field).3.to_string()
+    }
+}
+
+// statement writing one value of the field
+fn write_value(index: &Index, field: &FieldDescriptorProto, number: i32, value: &str) -> String {
+    let (_, method, wire, _) = scalar(field);
+    if field.r#type() == Type::Message {
+        format!(
+            "{}.encode({value}, writer.tag({number}, WireType.LengthDelimited).fork()).ldelim();",
+            named(index, field)
+        )
+    } else {
+        format!("writer.tag({number}, WireType.{wire}).{method}({value});")
+    }
+}
+
+// expression reading one value of the field
+fn read_value(index: &Index, field: &FieldDescriptorProto) -> String {
+    match field.r#type() {
+        Type::Message => format!("{}.decode(reader, reader.uint32())", named(index, field)),
+        Type::Enum => format!("reader.int32() as {}", named(index, field)),
+        _ => format!("reader.{}()", scalar(field).1),
+    }
+}
+
+fn packable(field: &FieldDescriptorProto) -> bool {
+    !matches!(
+        field.r#type(),
+        Type::String | Type::Bytes | Type::Message | Type::Group
+    )
+}
+
+fn encode_field(out: &mut String, index: &Index, field: &FieldDescriptorProto) {
+    let name = field.name();
+    let number = field.number();
+
+    if let Some((key, value)) = map_entry(index, field) {
+        let key_expr = if key.r#type() == Type::String {
+            "key"
+        } else {
+            "Number(key)"
+        };
+        let _ = writeln!(
+            out,
+            "    for (const [key, value] of Object.entries(message.{name})) {{"
+        );
+        let _ = writeln!(
+            out,
+            "      writer.tag({number}, WireType.LengthDelimited).fork();"
+        );
+        write_present(out, index, key, 1, key_expr, "      ");
+        write_present(out, index, value, 2, "value", "      ");
+        out.push_str("      writer.ldelim();\n    }\n");
+    } else if field.label() == Label::Repeated && packable(field) {
+        let _ = writeln!(out, "    if (message.{name}.length !== 0) {{");
+        let _ = writeln!(
+            out,
+            "      writer.tag({number}, WireType.LengthDelimited).fork();"
+        );
+        let _ = writeln!(out, "      for (const value of message.{name}) {{");
+        let _ = writeln!(out, "        writer.{}(value);", scalar(field).1);
+        out.push_str("      }\n      writer.ldelim();\n    }\n");
+    } else if field.label() == Label::Repeated {
+        let _ = writeln!(out, "    for (const value of message.{name}) {{");
+        let _ = writeln!(out, "      {}", write_value(index, field, number, "value"));
+        out.push_str("    }\n");
+    } else {
+        write_present(
+            out,
+            index,
+            field,
+            number,
+            &format!("message.{name}"),
+            "    ",
+        );
+    }
+}
+
+// writes a singular value unless it is the default, like prost, map values that are messages always go out
+fn write_present(
+    out: &mut String,
+    index: &Index,
+    field: &FieldDescriptorProto,
+    number: i32,
+    value: &str,
+    indent: &str,
+) {
+    let condition = if optional(field) {
+        format!("{value} != null")
+    } else {
+        match field.r#type() {
+            // map values, always written
+            Type::Message => {
+                let _ = writeln!(out, "{indent}{}", write_value(index, field, number, value));
+                return;
+            }
+            Type::Bytes => format!("{value}.length !== 0"),
+            Type::Bool => value.to_string(),
+            _ => format!("{value} !== {}", scalar(field).3),
+        }
+    };
+
+    let _ = writeln!(out, "{indent}if ({condition}) {{");
+    let _ = writeln!(
+        out,
+        "{indent}  {}",
+        write_value(index, field, number, value)
+    );
+    let _ = writeln!(out, "{indent}}}");
+}
+
+fn decode_field(out: &mut String, index: &Index, field: &FieldDescriptorProto) {
+    let name = field.name();
+    let _ = writeln!(out, "        case {}: {{", field.number());
+
+    if let Some((key, value)) = map_entry(index, field) {
+        out.push_str("          const entry = reader.end(reader.uint32());\n");
+        let _ = writeln!(out, "          let key = {};", default(index, key));
+        let _ = writeln!(out, "          let value = {};", default(index, value));
+        out.push_str("          while (reader.pos < entry) {\n");
+        out.push_str("            const [entryField, entryWireType] = reader.tag();\n");
+        let _ = writeln!(
+            out,
+            "            if (entryField === 1) {{\n              key = {};",
+            read_value(index, key)
+        );
+        let _ = writeln!(
+            out,
+            "            }} else if (entryField === 2) {{\n              value = {};",
+            read_value(index, value)
+        );
+        out.push_str(
+            "            } else {\n              reader.skip(entryWireType);\n            }\n",
+        );
+        out.push_str("          }\n");
+        let _ = writeln!(out, "          message.{name}[key] = value;");
+    } else if field.label() == Label::Repeated && packable(field) {
+        let read = read_value(index, field);
+        out.push_str("          if (wireType === WireType.LengthDelimited) {\n");
+        out.push_str("            const packed = reader.end(reader.uint32());\n");
+        out.push_str("            while (reader.pos < packed) {\n");
+        let _ = writeln!(out, "              message.{name}.push({read});");
+        out.push_str("            }\n          } else {\n");
+        let _ = writeln!(out, "            message.{name}.push({read});");
+        out.push_str("          }\n");
+    } else if field.label() == Label::Repeated {
+        let _ = writeln!(
+            out,
+            "          message.{name}.push({});",
+            read_value(index, field)
+        );
+    } else {
+        let _ = writeln!(
+            out,
+            "          message.{name} = {};",
+            read_value(index, field)
+        );
+    }
+
+    out.push_str("          break;\n        }\n");
+}
@@ -0,0 +1,39 @@
+use std::path::Path;
+
+use codegen::{SCHEMA_DIR, descriptors, stale};
+use server::manifest::{Body, ROUTES};
+
+#[test]
+fn committed_schema_is_current() {
+    let stale = stale(Path::new(SCHEMA_DIR));
+
+    assert!(
+        stale.is_empty(),
+        "run `cargo codegen`, out of date: {stale:?}"
+    );
+}
+
+#[test]
+fn manifest_messages_are_generated() {
+    let messages: Vec<String> = descriptors()
+        .file
+        .iter()
+        .flat_map(|file| {
+            file.message_type
+                .iter()
+                .map(move |message| format!("{}.{}", file.package(), message.name()))
+        })
+        .collect();
+
+    for route in ROUTES {
+        for body in route.request.iter().chain([&route.response]) {
+            if let Body::Message(message) = body {
+                assert!(
+                    messages.iter().any(|name| name == message),
+                    "{message} of {}",
+                    route.path
+                );
+            }
+        }
+    }
+}
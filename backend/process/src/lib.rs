@@ -12,6 +12,9 @@
 //!   updated when new food by adding another bit to each bitmap. No copy, just update each cron job. Zero out at the start of each cron job. Also must be
 //!   extended by a new bitmap when a new location is added.
 //!
+//! - Not built yet, foods carry their location as a string and the processor writes no bitmaps. Build them with
+//!   [`bank::bitmap::FoodBitmap`] so the frontend reads them like `schema/bitmap.ts`.
+//!
 //! ### External
 //! - External proto file for food name (**string**) in index ordering (implied index): Allows food indexing in bitmaps such as user vote bitmaps and location bitmaps.
 //!   Also allows syncing indexing between frontend (assuming dynamic fetch) and backend.
@@ -31,9 +34,9 @@
 //!   user votes on incrementing or decrementing.
 //!
 //! ### Meilisearch
-//! - Index for all foods (name: **string**, votes: **int**, location: **string**): Allows for user search of foods to find what to vote for.
-//!   Votes attribute will be synced every so often with Redis. Votes attribute allows for filtering in search and one less network call.
-//!   Location allows for filtering.
+//! - Index for all foods (name: **string**, votes: **int**, location: **string**, meals/tags: **string list**, served_today: **bool**):
+//!   Allows for user search of foods to find what to vote for. Votes attribute will be synced every so often with Redis. Votes attribute
+//!   allows for filtering in search and one less network call. Location, meals, tags and served_today allow for filtering and facets.
 //!
 //!
 //!
@@ -49,6 +52,12 @@
 //! - Fallback would be when reading a response that has more bits than the length of our proto file,
 //!   we ignore those bits and force a new fetch on the proto file.
 //!
+//! ## Search Vocabulary
+//! - Synonyms and stop words live in the bank, the server applies them to Meilisearch on its next index update
+//! - `process synonyms add "mac and cheese" "mac n cheese" macaroni` makes every term match the others
+//! - `process stop-words add the with` drops those words from queries
+//! - Both take `remove` and `list` too, terms are sanitized and lowercased like food names
+//!
 //! ## Daily Cron Job -- Purdue API
 //! 1. Keep a local copy of cron job foods for Meilisearch so we just need to modify this set to update.
 //!
@@ -77,6 +86,7 @@
 use std::collections::hash_map::Entry;
 
 use chrono::{Duration, NaiveDate};
+use clap::Subcommand;
 use indicatif::{ProgressBar, ProgressStyle};
 use reqwest::Client;
 
@@ -88,7 +98,26 @@ use bank::{
     get_bank, write_bank,
 };
 use models::{ENDPOINT, Response};
-use utils::{build_payload, format, reset_locations, sanitize, sanitize_bank, today};
+use utils::{
+    SanitizeReport, add_stop_words, add_synonyms, build_payload, format, remove_stop_words,
+    remove_synonyms, reset_locations, sanitize, sanitize_bank, tags, today,
+};
+
+#[derive(Subcommand, Debug)]
+pub enum Vocabulary {
+    /// Adds the terms, synonyms become one group matching each other
+    Add {
+        #[arg(required = true)]
+        terms: Vec<String>,
+    },
+    /// Removes the terms
+    Remove {
+        #[arg(required = true)]
+        terms: Vec<String>,
+    },
+    /// Prints every entry
+    List,
+}
 
 pub fn list_locations() {
     let bank = get_bank();
@@ -99,15 +128,71 @@ pub fn list_locations() {
     }
 }
 
+pub fn edit_synonyms(action: Vocabulary) {
+    let mut bank = get_bank();
+
+    match action {
+        Vocabulary::Add { terms } => {
+            let group = add_synonyms(&mut bank, &terms);
+            if group.is_empty() {
+                println!("Synonyms need at least two distinct terms. Exiting.");
+                return;
+            }
+            println!("Synonyms: {}", group.join(", "));
+        }
+        Vocabulary::Remove { terms } => {
+            println!("Removed Entries: {}", remove_synonyms(&mut bank, &terms));
+        }
+        Vocabulary::List => {
+            let mut entries: Vec<_> = bank.synonyms.iter().collect();
+            entries.sort_by_key(|(term, _)| *term);
+
+            println!("Synonyms:");
+            for (term, synonyms) in entries {
+                println!("{}: {}", term, synonyms.terms.join(", "));
+            }
+            return;
+        }
+    }
+
+    write_bank(&bank);
+}
+
+pub fn edit_stop_words(action: Vocabulary) {
+    let mut bank = get_bank();
+
+    match action {
+        Vocabulary::Add { terms } => {
+            println!("Added Stop Words: {}", add_stop_words(&mut bank, &terms));
+        }
+        Vocabulary::Remove { terms } => {
+            println!(
+                "Removed Stop Words: {}",
+                remove_stop_words(&mut bank, &terms)
+            );
+        }
+        Vocabulary::List => {
+            println!("Stop Words: {}", bank.stop_words.join(", "));
+            return;
+        }
+    }
+
+    write_bank(&bank);
+}
+
 pub async fn load_foods(days_before: u32, days_after: u32) {
     let mut bank = get_bank();
-    sanitize_bank(&mut bank);
+    if !report_sanitize(&sanitize_bank(&mut bank)) {
+        return;
+    }
     reset_locations(&mut bank);
 
     println!("Loaded Foods: {}", bank.foods.len());
     println!("Loaded Locations: {}\n", bank.locations.len());
 
     let (new_items, new_locations) = fetch_foods_range(&mut bank, days_before, days_after).await;
+    // the range always includes today, the only day locations and meals are kept for
+    bank.served_on = format(today());
 
     if new_items == 0 && new_locations == 0 {
         println!("No new items or locations found. Exiting.");
@@ -119,10 +204,25 @@ pub async fn load_foods(days_before: u32, days_after: u32) {
         println!("Location Verification: {}", bank.locations.len());
     }
 
-    sanitize_bank(&mut bank);
+    if !report_sanitize(&sanitize_bank(&mut bank)) {
+        return;
+    }
     write_bank(&bank);
 }
 
+fn report_sanitize(report: &SanitizeReport) -> bool {
+    if !report.is_empty() {
+        println!("{}", report);
+    }
+
+    if !report.is_resolved() {
+        println!("Unresolved collisions found. Refusing to write bank.");
+        return false;
+    }
+
+    true
+}
+
 async fn fetch_foods(bank: &mut Bank, client: &Client, date: NaiveDate) -> (usize, usize) {
     let payload = build_payload(&format(date));
     let res = client.post(ENDPOINT).json(&payload).send().await.unwrap();
@@ -133,11 +233,15 @@ async fn fetch_foods(bank: &mut Bank, client: &Client, date: NaiveDate) -> (usiz
     let json_string = res.text().await.unwrap();
     let json: Response = serde_json::from_str(&json_string).unwrap();
 
+    add_menu(bank, json, date == today())
+}
+
+// adds a day's menu to the bank, only today's menu sets locations and meals
+fn add_menu(bank: &mut Bank, json: Response, is_today: bool) -> (usize, usize) {
     let mut new_locations = 0;
     let mut new_items = 0;
 
     let mut location = String::new();
-    let is_today = date == today();
 
     for court in json.data.dining_courts {
         let sanitized_location = sanitize(&court.formal_name);
@@ -163,6 +267,8 @@ async fn fetch_foods(bank: &mut Bank, client: &Client, date: NaiveDate) -> (usiz
         }
 
         for meal in court.daily_menu.meals {
+            let sanitized_meal = sanitize(&meal.name);
+
             for station in meal.stations {
                 for item_shell in station.items {
                     let sanitized_food = sanitize(&item_shell.item.name);
@@ -171,22 +277,43 @@ async fn fetch_foods(bank: &mut Bank, client: &Client, date: NaiveDate) -> (usiz
                         continue;
                     }
 
-                    match bank.foods.entry(sanitized_food) {
+                    let food = match bank.foods.entry(sanitized_food) {
                         Entry::Vacant(entry) => {
                             #[cfg(feature = "verbose")]
                             println!("New item! {}", entry.key());
 
-                            entry.insert(Food {
+                            let food = entry.insert(Food {
                                 id: bank.next_food_id,
                                 location: location.clone(),
+                                ..Default::default()
                             });
 
                             bank.next_food_id += 1;
                             new_items += 1;
+
+                            food
                         }
-                        Entry::Occupied(mut entry) => {
-                            entry.get_mut().location = location.clone();
+                        Entry::Occupied(entry) => {
+                            let food = entry.into_mut();
+                            // other days of the range would clear a food served today
+                            if is_today {
+                                food.location = location.clone();
+                            }
+
+                            food
                         }
+                    };
+
+                    let item_tags = tags(item_shell.item.traits.as_deref().unwrap_or_default());
+                    if !item_tags.is_empty() {
+                        food.tags = item_tags;
+                    }
+
+                    if is_today
+                        && !sanitized_meal.is_empty()
+                        && !food.meals.contains(&sanitized_meal)
+                    {
+                        food.meals.push(sanitized_meal.clone());
                     }
                 }
             }
@@ -230,3 +357,43 @@ async fn fetch_foods_range(bank: &mut Bank, days_before: u32, days_after: u32) -
     pb.finish_with_message("Done");
     (new_items, new_locations)
 }
+
+#[cfg(test)]
+mod tests {
+    use bank::foods::Bank;
+
+    use super::add_menu;
+    use crate::models::Response;
+
+    fn menu(court: &str, meal: &str, items: &[&str]) -> Response {
+        let items: Vec<_> = items
+            .iter()
+            .map(|name| serde_json::json!({ "item": { "name": name, "traits": null } }))
+            .collect();
+
+        serde_json::from_value(serde_json::json!({
+            "data": { "diningCourts": [{
+                "formalName": court,
+                "dailyMenu": { "meals": [{ "name": meal, "stations": [{ "name": "Grill", "items": items }] }] }
+            }] }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_later_days_keep_todays_location() {
+        let mut bank = Bank::default();
+
+        add_menu(&mut bank, menu("Ford", "Lunch", &["Pho", "Curry"]), true);
+        add_menu(&mut bank, menu("Wiley", "Dinner", &["Pho", "Ramen"]), false);
+
+        let pho = &bank.foods["Pho"];
+        assert_eq!(pho.location, "Ford");
+        assert_eq!(pho.meals, vec!["Lunch".to_string()]);
+
+        // first seen on a later day, not served today
+        let ramen = &bank.foods["Ramen"];
+        assert_eq!(ramen.location, "");
+        assert!(ramen.meals.is_empty());
+    }
+}
@@ -1,10 +1,35 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use process::Vocabulary;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about)]
+#[command(
+    author,
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
-    days_before: u32,
-    days_after: u32,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(required = true)]
+    days_before: Option<u32>,
+    #[arg(required = true)]
+    days_after: Option<u32>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Search terms that match each other
+    Synonyms {
+        #[command(subcommand)]
+        action: Vocabulary,
+    },
+    /// Words searches ignore
+    StopWords {
+        #[command(subcommand)]
+        action: Vocabulary,
+    },
 }
 
 #[tokio::main]
@@ -16,5 +41,15 @@ async fn main() {
     }
 
     let args = Args::parse();
-    process::load_foods(args.days_before, args.days_after).await;
+    match args.command {
+        Some(Command::Synonyms { action }) => process::edit_synonyms(action),
+        Some(Command::StopWords { action }) => process::edit_stop_words(action),
+        None => {
+            // both are required without a subcommand
+            let (Some(days_before), Some(days_after)) = (args.days_before, args.days_after) else {
+                unreachable!()
+            };
+            process::load_foods(days_before, days_after).await;
+        }
+    }
 }
@@ -14,6 +14,9 @@ pub const QUERY: &str = r#"
                         items {
                             item {
                                 name
+                                traits {
+                                    name
+                                }
                             }
                         }
                     }
@@ -66,4 +69,11 @@ pub struct ItemShell {
 #[derive(Deserialize)]
 pub struct Item {
     pub name: String,
+    // null for items without nutrition info
+    pub traits: Option<Vec<Trait>>,
+}
+
+#[derive(Deserialize)]
+pub struct Trait {
+    pub name: String,
 }
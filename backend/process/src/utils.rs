@@ -1,22 +1,116 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    fmt::{self, Display},
+};
 
-use bank::foods::Bank;
+use bank::foods::{Bank, Food, Synonyms};
 use chrono::prelude::*;
 use regex::Regex;
 use serde_json::json;
 
-use crate::models::QUERY;
+use crate::models::{QUERY, Trait};
 
 pub fn reset_locations(bank: &mut Bank) {
-    // erase foods' location
+    // erase foods' location and meals, tags stay until the food is served again
     for value in bank.foods.values_mut() {
         value.location.clear();
+        value.meals.clear();
     }
 }
 
-pub fn sanitize_bank(bank: &mut Bank) {
-    sanitize_keys(&mut bank.foods);
-    sanitize_keys(&mut bank.locations);
+// sanitized, sorted and deduplicated trait names
+pub fn tags(traits: &[Trait]) -> Vec<String> {
+    let mut tags: Vec<String> = traits
+        .iter()
+        .map(|item_trait| sanitize(&item_trait.name))
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+
+    tags
+}
+
+// sanitized and lowercase, the form synonyms and stop words are kept in
+pub fn term(input: &str) -> String {
+    sanitize(input).to_lowercase()
+}
+
+// the terms match each other and every group they already belong to, returns the merged group
+pub fn add_synonyms(bank: &mut Bank, terms: &[String]) -> Vec<String> {
+    let mut group: Vec<String> = terms
+        .iter()
+        .map(|input| term(input))
+        .filter(|term| !term.is_empty())
+        .collect();
+    let joined: Vec<String> = group
+        .iter()
+        .filter_map(|term| bank.synonyms.get(term))
+        .flat_map(|synonyms| synonyms.terms.clone())
+        .collect();
+    group.extend(joined);
+    group.sort();
+    group.dedup();
+
+    if group.len() < 2 {
+        return Vec::new();
+    }
+
+    for term in &group {
+        let terms = group
+            .iter()
+            .filter(|other| *other != term)
+            .cloned()
+            .collect();
+        bank.synonyms.insert(term.clone(), Synonyms { terms });
+    }
+
+    group
+}
+
+// drops the terms from every group, a term left without synonyms is dropped too
+pub fn remove_synonyms(bank: &mut Bank, terms: &[String]) -> usize {
+    let terms: Vec<String> = terms.iter().map(|input| term(input)).collect();
+    let before = bank.synonyms.len();
+
+    bank.synonyms.retain(|key, synonyms| {
+        synonyms.terms.retain(|term| !terms.contains(term));
+        !terms.contains(key) && !synonyms.terms.is_empty()
+    });
+
+    before - bank.synonyms.len()
+}
+
+// kept sorted and deduplicated, returns how many were new
+pub fn add_stop_words(bank: &mut Bank, words: &[String]) -> usize {
+    let before = bank.stop_words.len();
+
+    bank.stop_words.extend(
+        words
+            .iter()
+            .map(|input| term(input))
+            .filter(|word| !word.is_empty()),
+    );
+    bank.stop_words.sort();
+    bank.stop_words.dedup();
+
+    bank.stop_words.len() - before
+}
+
+pub fn remove_stop_words(bank: &mut Bank, words: &[String]) -> usize {
+    let words: Vec<String> = words.iter().map(|input| term(input)).collect();
+    let before = bank.stop_words.len();
+
+    bank.stop_words.retain(|word| !words.contains(word));
+
+    before - bank.stop_words.len()
+}
+
+pub fn sanitize_bank(bank: &mut Bank) -> SanitizeReport {
+    SanitizeReport {
+        foods: sanitize_keys(&mut bank.foods, &mut bank.food_aliases),
+        locations: sanitize_keys(&mut bank.locations, &mut bank.location_aliases),
+    }
 }
 
 pub fn build_payload(date: &str) -> serde_json::Value {
@@ -35,10 +129,113 @@ pub fn format(date: NaiveDate) -> String {
     date.format("%Y-%m-%d").to_string()
 }
 
-pub fn sanitize_keys<V>(map: &mut HashMap<String, V>) {
-    let new_map: HashMap<String, V> = map.drain().map(|(k, v)| (sanitize(&k), v)).collect();
+pub trait Identified {
+    fn id(&self) -> u32;
+}
+
+impl Identified for Food {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl Identified for u32 {
+    fn id(&self) -> u32 {
+        *self
+    }
+}
+
+pub struct Collision {
+    pub key: String,
+    pub raw_key: String,
+    pub kept_id: u32,
+    pub alias_id: u32,
+}
 
-    *map = new_map;
+#[derive(Default)]
+pub struct KeyReport {
+    pub collisions: Vec<Collision>,
+    // raw keys that sanitize to nothing, cannot be kept or aliased
+    pub unresolved: Vec<String>,
+}
+
+pub struct SanitizeReport {
+    pub foods: KeyReport,
+    pub locations: KeyReport,
+}
+
+impl SanitizeReport {
+    pub fn is_resolved(&self) -> bool {
+        self.foods.unresolved.is_empty() && self.locations.unresolved.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.is_resolved()
+            && self.foods.collisions.is_empty()
+            && self.locations.collisions.is_empty()
+    }
+}
+
+impl Display for SanitizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (label, report) in [("Food", &self.foods), ("Location", &self.locations)] {
+            for collision in &report.collisions {
+                writeln!(
+                    f,
+                    "{label} collision: \"{}\" ({}) merged into \"{}\" ({})",
+                    collision.raw_key, collision.alias_id, collision.key, collision.kept_id
+                )?;
+            }
+
+            for raw_key in &report.unresolved {
+                writeln!(
+                    f,
+                    "{label} unresolved: \"{raw_key}\" sanitizes to an empty key"
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn sanitize_keys<V: Identified>(
+    map: &mut HashMap<String, V>,
+    aliases: &mut HashMap<u32, u32>,
+) -> KeyReport {
+    let mut report = KeyReport::default();
+
+    // lowest id first so the oldest entry always wins a collision
+    let mut entries: Vec<(String, V)> = map.drain().collect();
+    entries.sort_by_key(|(_, value)| value.id());
+
+    for (raw_key, value) in entries {
+        let key = sanitize(&raw_key);
+
+        if key.is_empty() {
+            report.unresolved.push(raw_key);
+            continue;
+        }
+
+        match map.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+            Entry::Occupied(entry) => {
+                let kept_id = entry.get().id();
+                aliases.insert(value.id(), kept_id);
+
+                report.collisions.push(Collision {
+                    key: entry.key().clone(),
+                    raw_key,
+                    kept_id,
+                    alias_id: value.id(),
+                });
+            }
+        }
+    }
+
+    report
 }
 
 pub fn sanitize(input: &str) -> String {
@@ -56,7 +253,23 @@ pub fn sanitize(input: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::sanitize;
+    use std::collections::HashMap;
+
+    use bank::foods::Bank;
+
+    use super::{
+        add_stop_words, add_synonyms, remove_stop_words, remove_synonyms, sanitize, sanitize_keys,
+        tags,
+    };
+    use crate::models::Trait;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn synonyms_of<'a>(bank: &'a Bank, term: &str) -> &'a [String] {
+        &bank.synonyms[term].terms
+    }
 
     #[test]
     fn test_basic() {
@@ -88,4 +301,102 @@ mod tests {
         assert_eq!(sanitize(""), "");
         assert_eq!(sanitize("     "), "");
     }
+
+    #[test]
+    fn test_sanitize_keys_collision() {
+        let mut map = HashMap::from([
+            ("Fried_Rice".to_string(), 7),
+            ("Fried Rice!".to_string(), 3),
+            ("Pho".to_string(), 5),
+        ]);
+        let mut aliases = HashMap::new();
+
+        let report = sanitize_keys(&mut map, &mut aliases);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["Fried Rice"], 3);
+        assert_eq!(aliases, HashMap::from([(7, 3)]));
+        assert_eq!(report.collisions.len(), 1);
+        assert_eq!(report.collisions[0].raw_key, "Fried_Rice");
+        assert!(report.unresolved.is_empty());
+    }
+
+    #[test]
+    fn test_sanitize_keys_unresolved() {
+        let mut map = HashMap::from([("!!!".to_string(), 1), ("Pho".to_string(), 2)]);
+        let mut aliases = HashMap::new();
+
+        let report = sanitize_keys(&mut map, &mut aliases);
+
+        assert_eq!(map.len(), 1);
+        assert!(aliases.is_empty());
+        assert_eq!(report.unresolved, vec!["!!!".to_string()]);
+    }
+
+    #[test]
+    fn test_tags() {
+        let traits = ["Vegan", "Gluten_Free", "Vegan", "!!!"].map(|name| Trait {
+            name: name.to_string(),
+        });
+
+        assert_eq!(tags(&traits), vec!["Gluten Free", "Vegan"]);
+    }
+
+    #[test]
+    fn test_add_synonyms_merges_groups() {
+        let mut bank = Bank::default();
+
+        let group = add_synonyms(&mut bank, &strings(&["Mac and Cheese", "mac n' cheese"]));
+        assert_eq!(group, strings(&["mac and cheese", "mac n cheese"]));
+
+        add_synonyms(&mut bank, &strings(&["macaroni", "mac n cheese"]));
+        assert_eq!(bank.synonyms.len(), 3);
+        assert_eq!(
+            synonyms_of(&bank, "macaroni"),
+            strings(&["mac and cheese", "mac n cheese"])
+        );
+        assert_eq!(
+            synonyms_of(&bank, "mac and cheese"),
+            strings(&["mac n cheese", "macaroni"])
+        );
+
+        // a lone term is not a group
+        assert!(add_synonyms(&mut bank, &strings(&["pho", "!!!"])).is_empty());
+        assert!(!bank.synonyms.contains_key("pho"));
+    }
+
+    #[test]
+    fn test_remove_synonyms() {
+        let mut bank = Bank::default();
+        add_synonyms(
+            &mut bank,
+            &strings(&["mac and cheese", "mac n cheese", "macaroni"]),
+        );
+        add_synonyms(&mut bank, &strings(&["soda", "pop"]));
+
+        assert_eq!(remove_synonyms(&mut bank, &strings(&["Macaroni"])), 1);
+        assert_eq!(
+            synonyms_of(&bank, "mac n cheese"),
+            strings(&["mac and cheese"])
+        );
+
+        // pop is left without synonyms
+        assert_eq!(remove_synonyms(&mut bank, &strings(&["soda"])), 2);
+        assert_eq!(bank.synonyms.len(), 2);
+    }
+
+    #[test]
+    fn test_stop_words() {
+        let mut bank = Bank::default();
+
+        assert_eq!(
+            add_stop_words(&mut bank, &strings(&["The", "and", "the"])),
+            2
+        );
+        assert_eq!(add_stop_words(&mut bank, &strings(&["and", "with"])), 1);
+        assert_eq!(bank.stop_words, strings(&["and", "the", "with"]));
+
+        assert_eq!(remove_stop_words(&mut bank, &strings(&["AND", "of"])), 1);
+        assert_eq!(bank.stop_words, strings(&["the", "with"]));
+    }
 }
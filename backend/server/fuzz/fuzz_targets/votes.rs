@@ -0,0 +1,69 @@
+//! Untrusted vote payloads through protobuf decoding, the bank version check and the bitmap diff.
+//!
+//! ```sh
+//! cd backend/server
+//! cargo +nightly fuzz run votes
+//! ```
+//!
+//! Crashes land in `fuzz/artifacts/votes`, copy the input into `fuzz/corpus/votes` once fixed.
+#![no_main]
+
+use std::collections::HashMap;
+
+use bank::{bitmap::FoodBitmap, get_votes_from_bytes};
+use libfuzzer_sys::fuzz_target;
+use server::{database::Vote, utils::diff_votes};
+
+// 40 foods, every fifth id removed from the bank
+const FOODS: usize = 40;
+const MAX_VOTE_FLIPS: usize = 16;
+
+fuzz_target!(|data: &[u8]| {
+    let names: Vec<String> = (0..FOODS)
+        .map(|id| {
+            if id % 5 == 4 {
+                String::new()
+            } else {
+                format!("food {id}")
+            }
+        })
+        .collect();
+
+    let decoded = get_votes_from_bytes(data);
+
+    let Ok((votes, bit_map)) = diff_votes(&names, &HashMap::new(), MAX_VOTE_FLIPS, data) else {
+        return;
+    };
+    let maps = decoded.expect("diff accepted a payload that does not decode");
+
+    // unversioned payloads are assumed to be current
+    let client = match maps.next_food_id {
+        0 => FOODS,
+        client => client as usize,
+    };
+    assert!(client <= FOODS);
+
+    assert_eq!(maps.old_bit_map.len(), maps.new_bit_map.len());
+    assert!(maps.old_bit_map.len() <= FoodBitmap::byte_len(client as u32));
+    assert!(votes.len() <= MAX_VOTE_FLIPS);
+    assert_eq!(bit_map.len(), FoodBitmap::byte_len(FOODS as u32));
+
+    let mut flipped = 0;
+    for (id, (old, new)) in maps.old_bit_map.iter().zip(&maps.new_bit_map).enumerate() {
+        for bit in 0..8 {
+            let food = id * 8 + bit;
+            if food < client && !names[food].is_empty() && (old ^ new) >> bit & 1 == 1 {
+                flipped += 1;
+            }
+        }
+    }
+    assert_eq!(votes.len(), flipped);
+
+    for (food, vote) in &votes {
+        let food = *food as usize;
+        assert!(food < client && !names[food].is_empty());
+
+        let new_bit = maps.new_bit_map[food / 8] >> (food % 8) & 1;
+        assert_eq!(matches!(vote, Vote::Increment), new_bit == 1);
+    }
+});
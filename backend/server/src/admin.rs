@@ -0,0 +1,363 @@
+//! # Admin
+//!
+//! Operator endpoints under `/admin`, authenticated with `Authorization: Bearer <ADMIN_KEY>`.
+//!
+//! ## Endpoints
+//! - `GET /admin/jobs`: status of every cron job, see [`crate::jobs`]
+//! - `POST /admin/jobs/{name}/run`: runs a job now and returns its status once done
+//! - `GET /admin/foods/{id or name}`: bank entry, Redis votes and Meilisearch document of a food,
+//!   `document` is null while search is down
+//! - `POST /admin/foods/{id or name}/votes`: `{"set": 0}` resets, `{"adjust": -3}` shifts, never below 0
+//! - `GET /admin/foods/{id or name}/history?bucket=hour|day&count=`: vote delta per hour or day, oldest first
+//! - `GET /admin/users/{valid_id}`: latest vote bitmap of a user and the food ids it votes for
+//! - `POST /admin/bank/refresh`: runs the `bank_refresh` job now
+//! - `POST /admin/index/rebuild`: rebuilds the Meilisearch index from the bank and Redis and swaps it in
+//! - `GET /admin/audit?limit=`: latest changes, newest first, see [`crate::audit`]
+//!
+//! ## Notes
+//! - Without `ADMIN_KEY` every request is rejected
+//! - Skips the token cookie check, meant for curl rather than the browser
+//! - Every `POST` goes to the audit log, failed ones included
+//!
+//! ## Commands
+//!
+//! Inspect a food.
+//! ```sh
+//! curl -H "Authorization: Bearer $ADMIN_KEY" "http://localhost:1000/admin/foods/Dill%20Pickle%20Slices"
+//! ```
+//!
+//! Its votes over the last two weeks.
+//! ```sh
+//! curl -H "Authorization: Bearer $ADMIN_KEY" "http://localhost:1000/admin/foods/7/history?bucket=day&count=14"
+//! ```
+//!
+//! Reset its votes.
+//! ```sh
+//! curl -X POST -H "Authorization: Bearer $ADMIN_KEY" -H "X-Admin-User: $USER" \
+//!     -H "Content-Type: application/json" -d '{"set": 0}' http://localhost:1000/admin/foods/7/votes
+//! ```
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, Query, Request, State},
+    http::{HeaderMap, header::AUTHORIZATION},
+    middleware::Next,
+    response::Response,
+};
+use bank::{RemoteBank, bitmap::FoodBitmap, foods::Food};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    audit::{AuditEntry, audit, get_audit_log},
+    database::{AUDIT_LENGTH, HOURS_PER_DAY, VoteChange},
+    error::AppError::{self, NotFound, Unauthorized},
+    jobs::{JobKind, JobStatus, Outcome, run_job},
+    search::MeiliFood,
+    state::State as AppState,
+};
+
+const BEARER: &str = "Bearer ";
+const DEFAULT_AUDIT_LIMIT: usize = 50;
+const DEFAULT_HISTORY_COUNT: u64 = 24;
+// a year of days, hours are capped by the retention instead
+const MAX_HISTORY_DAYS: u64 = 366;
+const HOUR_MS: u64 = 60 * 60 * 1000;
+
+#[derive(Serialize)]
+pub struct FoodReport {
+    pub id: u32,
+    pub name: String,
+    pub location: String,
+    pub aliases: Vec<u32>,
+    pub votes: Option<i64>,
+    pub document: Option<MeiliFood>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryBucket {
+    #[default]
+    Hour,
+    Day,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub bucket: Option<HistoryBucket>,
+    pub count: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryPoint {
+    pub start_ms: u64,
+    pub delta: i64,
+}
+
+#[derive(Serialize)]
+pub struct VoteHistory {
+    pub id: u32,
+    pub name: String,
+    pub bucket: HistoryBucket,
+    pub points: Vec<HistoryPoint>,
+}
+
+#[derive(Serialize)]
+pub struct VotesChanged {
+    pub id: u32,
+    pub name: String,
+    pub before: i64,
+    pub after: i64,
+}
+
+#[derive(Serialize)]
+pub struct UserReport {
+    pub user: String,
+    pub bit_map: String,
+    pub food_ids: Vec<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    pub limit: Option<usize>,
+}
+
+pub async fn verify_admin(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let key = state.config.admin_key.as_deref().ok_or(Unauthorized)?;
+
+    let provided = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix(BEARER))
+        .ok_or(Unauthorized)?;
+
+    // comparing digests keeps the comparison time independent of the key
+    if Sha256::digest(provided) != Sha256::digest(key) {
+        return Err(Unauthorized);
+    }
+
+    Ok(next.run(request).await)
+}
+
+pub async fn jobs_handler(State(state): State<Arc<AppState>>) -> Json<Vec<JobStatus>> {
+    Json(state.jobs.statuses().await)
+}
+
+pub async fn run_job_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<JobStatus>, AppError> {
+    let kind: JobKind = name.parse()?;
+
+    audited_run(&state, &headers, "jobs.run", kind).await
+}
+
+pub async fn bank_refresh_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<JobStatus>, AppError> {
+    audited_run(&state, &headers, "bank.refresh", JobKind::BankRefresh).await
+}
+
+pub async fn food_handler(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+) -> Result<Json<FoodReport>, AppError> {
+    let remote_bank = state.remote_bank.load();
+    let (name, food) = find_food(&remote_bank, &key)?;
+
+    let votes = state.store()?.votes(food.id).await?;
+    // the store and bank answer on their own while search is down or not indexed yet
+    let document = match state.search() {
+        Ok(search) => search.food_document(food.id).await.ok().flatten(),
+        Err(_) => None,
+    };
+
+    let mut aliases: Vec<u32> = remote_bank
+        .alias_to_kept
+        .iter()
+        .filter(|(_, kept_id)| **kept_id == food.id)
+        .map(|(alias_id, _)| *alias_id)
+        .collect();
+    aliases.sort_unstable();
+
+    Ok(Json(FoodReport {
+        id: food.id,
+        name: name.to_string(),
+        location: food.location.clone(),
+        aliases,
+        votes,
+        document,
+    }))
+}
+
+pub async fn history_handler(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<VoteHistory>, AppError> {
+    let remote_bank = state.remote_bank.load();
+    let (name, food) = find_food(&remote_bank, &key)?;
+
+    let bucket = query.bucket.unwrap_or_default();
+    let count = query.count.unwrap_or(DEFAULT_HISTORY_COUNT);
+    let store = state.store()?;
+
+    // hours past the retention are compacted and would read as 0
+    let (history, bucket_ms) = match bucket {
+        HistoryBucket::Hour => {
+            let hours = count.clamp(1, state.config.hourly_retention_days * HOURS_PER_DAY);
+            (store.hourly_history(food.id, hours).await?, HOUR_MS)
+        }
+        HistoryBucket::Day => {
+            let days = count.clamp(1, MAX_HISTORY_DAYS);
+            (
+                store.daily_history(food.id, days).await?,
+                HOURS_PER_DAY * HOUR_MS,
+            )
+        }
+    };
+
+    Ok(Json(VoteHistory {
+        id: food.id,
+        name: name.to_string(),
+        bucket,
+        points: history
+            .into_iter()
+            .map(|(index, delta)| HistoryPoint {
+                start_ms: index * bucket_ms,
+                delta,
+            })
+            .collect(),
+    }))
+}
+
+pub async fn change_votes_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(key): Path<String>,
+    Json(change): Json<VoteChange>,
+) -> Result<Json<VotesChanged>, AppError> {
+    let result = async {
+        let remote_bank = state.remote_bank.load();
+        let (name, food) = find_food(&remote_bank, &key)?;
+
+        let (before, after) = state
+            .store()?
+            .change_votes(food.id, change)
+            .await?
+            .ok_or_else(|| NotFound(format!("votes for food {}", food.id)))?;
+
+        Ok(VotesChanged {
+            id: food.id,
+            name: name.to_string(),
+            before,
+            after,
+        })
+    }
+    .await;
+
+    let outcome = result
+        .as_ref()
+        .map(|changed| format!("{:?}: {} -> {}", change, changed.before, changed.after));
+    audit(&state, &headers, "votes.change", &key, outcome).await;
+
+    result.map(Json)
+}
+
+pub async fn user_handler(
+    State(state): State<Arc<AppState>>,
+    Path(user): Path<String>,
+) -> Result<Json<UserReport>, AppError> {
+    let bit_map = state
+        .store()?
+        .user_votes(&user)
+        .await?
+        .map(FoodBitmap::from_bytes)
+        .ok_or_else(|| NotFound(format!("votes for user {user}")))?;
+
+    Ok(Json(UserReport {
+        user,
+        bit_map: hex::encode(bit_map.as_bytes()),
+        food_ids: bit_map.iter_ones().collect(),
+    }))
+}
+
+pub async fn rebuild_index_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<usize>, AppError> {
+    let result = async {
+        let food_votes = state.store()?.food_votes().await?;
+
+        state.rebuild_search(&food_votes).await
+    }
+    .await;
+
+    let outcome = result
+        .as_ref()
+        .map(|foods| format!("Indexed {foods} foods"));
+    audit(&state, &headers, "index.rebuild", "foods", outcome).await;
+
+    result.map(Json)
+}
+
+pub async fn audit_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, AppError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_LIMIT)
+        .clamp(1, AUDIT_LENGTH);
+
+    Ok(Json(get_audit_log(&state, limit).await?))
+}
+
+async fn audited_run(
+    state: &Arc<AppState>,
+    headers: &HeaderMap,
+    action: &str,
+    kind: JobKind,
+) -> Result<Json<JobStatus>, AppError> {
+    let result = run_job(state, kind).await;
+
+    let outcome = result.as_ref().map(|status| match &status.last_run {
+        Some(run) if matches!(run.outcome, Outcome::Failed) => format!("failed: {}", run.message),
+        Some(run) => run.message.clone(),
+        None => String::new(),
+    });
+    audit(state, headers, action, kind.name(), outcome).await;
+
+    result.map(Json)
+}
+
+// numeric keys are food ids, aliases resolve to the kept id, anything else is a name
+fn find_food<'a>(remote_bank: &'a RemoteBank, key: &str) -> Result<(&'a str, &'a Food), AppError> {
+    let name = match key.parse::<u32>() {
+        Ok(id) => {
+            let id = remote_bank.alias_to_kept.get(&id).copied().unwrap_or(id);
+
+            remote_bank
+                .food_id_to_name
+                .get(id as usize)
+                .map(String::as_str)
+                .unwrap_or_default()
+        }
+        Err(_) => key,
+    };
+
+    remote_bank
+        .bank
+        .foods
+        .get_key_value(name)
+        .map(|(name, food)| (name.as_str(), food))
+        .ok_or_else(|| NotFound(format!("food {key}")))
+}
@@ -0,0 +1,80 @@
+//! # Audit
+//!
+//! Record of every change made through the admin API.
+//!
+//! ## Storage
+//! - Vote store list, newest first, trimmed to the last 1000 entries, `audit` in Redis
+//! - Every entry is also logged under the `audit` target, so nothing is lost while the store is down
+//!
+//! ## Actor
+//! - Taken from the `X-Admin-User` header, `admin` if missing, as the admin key is shared
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    error::AppError::{self, InternalError},
+    state::State,
+    utils::now_ms,
+};
+
+const ACTOR_HEADER: &str = "x-admin-user";
+const DEFAULT_ACTOR: &str = "admin";
+
+#[derive(Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at_ms: u64,
+    pub actor: String,
+    pub action: String,
+    pub subject: String,
+    pub outcome: String,
+}
+
+// outcome is a short summary of the change, or the error that stopped it
+pub async fn audit(
+    state: &State,
+    headers: &HeaderMap,
+    action: &str,
+    subject: &str,
+    outcome: Result<String, &AppError>,
+) {
+    let entry = AuditEntry {
+        at_ms: now_ms(),
+        actor: headers
+            .get(ACTOR_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or(DEFAULT_ACTOR)
+            .to_string(),
+        action: action.to_string(),
+        subject: subject.to_string(),
+        outcome: outcome.unwrap_or_else(|e| format!("failed: {e}")),
+    };
+
+    info!(
+        target: "audit",
+        actor = entry.actor,
+        action = entry.action,
+        subject = entry.subject,
+        outcome = entry.outcome,
+        "Admin change"
+    );
+
+    let stored = match (state.store(), serde_json::to_string(&entry)) {
+        (Ok(store), Ok(json)) => store.push_audit(&json).await,
+        (Err(e), _) => Err(e),
+        (_, Err(e)) => Err(InternalError(Box::new(e))),
+    };
+
+    if let Err(e) = stored {
+        warn!("Failed to store audit entry: {e}");
+    }
+}
+
+pub async fn get_audit_log(state: &State, count: usize) -> Result<Vec<AuditEntry>, AppError> {
+    let entries = state.store()?.audit(count).await?;
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| serde_json::from_str(entry).ok())
+        .collect())
+}
@@ -1,56 +1,478 @@
-use std::{env, fmt::Display, str::FromStr};
+//! # Config
+//!
+//! Layered configuration, later layers override earlier ones.
+//!
+//! 1. Defaults
+//! 2. TOML file at `CONFIG_PATH` (default `config.toml`), skipped if missing
+//! 3. Environment variables
+//! 4. Docker secrets in `/run/secrets`, only for secret keys
+//!
+//! ## Validation
+//! - Every key is parsed and checked before the server starts
+//! - All problems are reported at once, not just the first
+//! - Unknown keys in the TOML file are rejected to catch typos
+//!
+//! ## Print
+//! Show the resolved config with secrets redacted, `Debug` prints the same.
+//! ```sh
+//! cargo server -- --print-config
+//! ```
+//!
+//! ## Tests
+//! [`Config::from_toml`] reads only the given TOML, environment and secrets are ignored.
+//!
+//! See `server/config.example.toml` for every key.
+use std::{
+    collections::HashSet,
+    env,
+    fmt::{self, Display},
+    fs::read_to_string,
+    io::ErrorKind,
+    str::FromStr,
+};
 
-use std::fs::read_to_string;
+use serde::{Serialize, Serializer};
+use thiserror::Error;
+use tokio_cron_scheduler::Job;
+use toml::{Table, Value};
+use tracing::info;
 
-use tracing::{info, warn};
+use crate::{limiter::TrustedProxies, store::StoreKind};
 
+const CONFIG_PATH: &str = "CONFIG_PATH";
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+const SECRETS_DIR: &str = "/run/secrets";
+const REDACTED: &str = "<redacted>";
+
+#[derive(Serialize)]
 pub struct Config {
     pub port: u16,
+    #[serde(serialize_with = "redact")]
     pub meili_key: String,
     pub meili_url: String,
     pub redis_url: String,
+    pub vote_store: StoreKind,
+    pub ip_rate_burst: u32,
+    pub ip_rate_refill_ms: u64,
+    pub trusted_proxies: TrustedProxies,
+    pub max_vote_flips: usize,
+    pub search_max_limit: usize,
+    pub search_max_offset: usize,
+    pub search_typo_one_min: u8,
+    pub search_typo_two_min: u8,
+    // whether the server checks the token cookie itself, token_key is only loaded when it does
+    pub verify_token: bool,
+    #[serde(serialize_with = "redact_option")]
+    pub token_key: Option<String>,
+    pub token_expiry_ms: u64,
+    pub token_debounce_ms: u64,
+    pub token_refresh_ms: u64,
+    pub hourly_retention_days: u64,
+    pub startup_retries: u32,
+    pub rebuild_on_startup: bool,
+    pub otlp_endpoint: Option<String>,
+    pub bank_refresh_schedule: String,
+    pub vote_sync_schedule: String,
+    pub cleanup_schedule: String,
+    pub snapshot_schedule: String,
+    pub snapshot_dir: String,
+    pub snapshot_keep: usize,
+    #[serde(serialize_with = "redact_option")]
+    pub admin_key: Option<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("{path}: {reason}")]
+    File { path: String, reason: String },
+
+    #[error("{key}: unknown key in config file")]
+    Unknown { key: String },
+
+    #[error("{key}: missing, set it in the config file, {env} or {SECRETS_DIR}/{env}")]
+    Missing {
+        key: &'static str,
+        env: &'static str,
+    },
+
+    #[error("{key}: invalid value \"{value}\": {reason}")]
+    Parse {
+        key: &'static str,
+        value: String,
+        reason: String,
+    },
+
+    #[error("{key}: {reason}")]
+    Invalid { key: &'static str, reason: String },
 }
 
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Config has {} problem(s):", self.0.len())?;
+
+        for error in &self.0 {
+            writeln!(f, "  - {error}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
 impl Config {
-    pub fn load() -> Self {
-        Self {
-            port: try_load("RUST_PORT", "1000"),
-            meili_key: read_secret("MEILI_ADMIN_KEY"),
-            meili_url: try_load("MEILI_URL", "http://meilisearch:7700"),
-            redis_url: try_load("REDIS_URL", "redis://redis:6379"),
+    pub fn load() -> Result<Self, ConfigErrors> {
+        Self::from_loader(Loader::new())
+    }
+
+    // defaults and the given file only, so tests never pick up the host environment
+    pub fn from_toml(contents: &str) -> Result<Self, ConfigErrors> {
+        let file = contents.parse::<Table>().map_err(|e| {
+            ConfigErrors(vec![ConfigError::File {
+                path: "<inline>".to_string(),
+                reason: e.to_string(),
+            }])
+        })?;
+
+        Self::from_loader(Loader {
+            file,
+            layered: false,
+            used: HashSet::new(),
+            errors: Vec::new(),
+        })
+    }
+
+    fn from_loader(mut loader: Loader) -> Result<Self, ConfigErrors> {
+        let verify_token: bool = loader.load("verify_token", "VERIFY_TOKEN", "true");
+
+        let config = Self {
+            port: loader.load("port", "RUST_PORT", "1000"),
+            meili_key: loader.secret("meili_key", "MEILI_ADMIN_KEY"),
+            meili_url: loader.load("meili_url", "MEILI_URL", "http://meilisearch:7700"),
+            redis_url: loader.load("redis_url", "REDIS_URL", "redis://redis:6379"),
+            vote_store: loader.load("vote_store", "VOTE_STORE", "redis"),
+            ip_rate_burst: loader.load("ip_rate_burst", "IP_RATE_BURST", "60"),
+            ip_rate_refill_ms: loader.load("ip_rate_refill_ms", "IP_RATE_REFILL_MS", "250"),
+            trusted_proxies: loader.load("trusted_proxies", "TRUSTED_PROXIES", ""),
+            max_vote_flips: loader.load("max_vote_flips", "MAX_VOTE_FLIPS", "64"),
+            search_max_limit: loader.load("search_max_limit", "SEARCH_MAX_LIMIT", "100"),
+            search_max_offset: loader.load("search_max_offset", "SEARCH_MAX_OFFSET", "1000"),
+            search_typo_one_min: loader.load("search_typo_one_min", "SEARCH_TYPO_ONE_MIN", "5"),
+            search_typo_two_min: loader.load("search_typo_two_min", "SEARCH_TYPO_TWO_MIN", "9"),
+            verify_token,
+            // disable when running behind rpxy, which already verifies the token
+            token_key: verify_token.then(|| loader.secret("token_key", "JWT_KEY")),
+            token_expiry_ms: loader.load("token_expiry_ms", "TOKEN_EXPIRY_MS", "300000"),
+            token_debounce_ms: loader.load("token_debounce_ms", "TOKEN_DEBOUNCE_MS", "200"),
+            token_refresh_ms: loader.load("token_refresh_ms", "TOKEN_REFRESH_MS", "240000"),
+            hourly_retention_days: loader.load(
+                "hourly_retention_days",
+                "HOURLY_RETENTION_DAYS",
+                "8",
+            ),
+            startup_retries: loader.load("startup_retries", "STARTUP_RETRIES", "10"),
+            rebuild_on_startup: loader.load("rebuild_on_startup", "REBUILD_ON_STARTUP", "true"),
+            otlp_endpoint: loader.optional("otlp_endpoint", "OTLP_ENDPOINT"),
+            bank_refresh_schedule: loader.load(
+                "bank_refresh_schedule",
+                "BANK_REFRESH_SCHEDULE",
+                "0 0 4 * * *",
+            ),
+            vote_sync_schedule: loader.load(
+                "vote_sync_schedule",
+                "VOTE_SYNC_SCHEDULE",
+                "0 */5 * * * *",
+            ),
+            cleanup_schedule: loader.load("cleanup_schedule", "CLEANUP_SCHEDULE", "0 5 * * * *"),
+            snapshot_schedule: loader.load(
+                "snapshot_schedule",
+                "SNAPSHOT_SCHEDULE",
+                "0 30 * * * *",
+            ),
+            snapshot_dir: loader.load("snapshot_dir", "SNAPSHOT_DIR", "snapshots"),
+            snapshot_keep: loader.load("snapshot_keep", "SNAPSHOT_KEEP", "48"),
+            // admin endpoints reject every request without it
+            admin_key: loader.optional_secret("admin_key", "ADMIN_KEY"),
+        };
+
+        loader.reject_unknown();
+        config.validate(&mut loader.errors);
+
+        if !loader.errors.is_empty() {
+            return Err(ConfigErrors(loader.errors));
+        }
+
+        Ok(config)
+    }
+
+    fn validate(&self, errors: &mut Vec<ConfigError>) {
+        // keys that failed to parse hold placeholders, do not report them twice
+        let unparsed: HashSet<&'static str> = errors
+            .iter()
+            .filter_map(|error| match error {
+                ConfigError::Parse { key, .. } | ConfigError::Missing { key, .. } => Some(*key),
+                _ => None,
+            })
+            .collect();
+
+        let mut check = |failed: bool, key: &'static str, reason: &str| {
+            if failed && !unparsed.contains(key) {
+                errors.push(ConfigError::Invalid {
+                    key,
+                    reason: reason.to_string(),
+                });
+            }
+        };
+
+        check(self.port == 0, "port", "must not be 0");
+        check(
+            !is_url(&self.meili_url, &["http://", "https://"]),
+            "meili_url",
+            "must start with http:// or https://",
+        );
+        check(
+            !is_url(&self.redis_url, &["redis://", "rediss://"]),
+            "redis_url",
+            "must start with redis:// or rediss://",
+        );
+        check(
+            self.ip_rate_burst == 0,
+            "ip_rate_burst",
+            "must be at least 1",
+        );
+        check(
+            self.ip_rate_refill_ms == 0,
+            "ip_rate_refill_ms",
+            "must be at least 1",
+        );
+        check(
+            self.max_vote_flips == 0,
+            "max_vote_flips",
+            "must be at least 1",
+        );
+        check(
+            self.search_max_limit == 0,
+            "search_max_limit",
+            "must be at least 1",
+        );
+        check(
+            self.search_typo_one_min > self.search_typo_two_min,
+            "search_typo_one_min",
+            "must not exceed search_typo_two_min",
+        );
+        check(
+            self.token_debounce_ms >= self.token_expiry_ms,
+            "token_debounce_ms",
+            "must be shorter than token_expiry_ms",
+        );
+        check(
+            self.token_refresh_ms > self.token_expiry_ms,
+            "token_refresh_ms",
+            "must not exceed token_expiry_ms",
+        );
+        check(
+            self.hourly_retention_days == 0,
+            "hourly_retention_days",
+            "must be at least 1",
+        );
+        check(
+            self.startup_retries == 0,
+            "startup_retries",
+            "must be at least 1",
+        );
+        check(
+            self.otlp_endpoint
+                .as_deref()
+                .is_some_and(|url| !is_url(url, &["http://", "https://"])),
+            "otlp_endpoint",
+            "must start with http:// or https://",
+        );
+
+        check(
+            self.snapshot_dir.is_empty(),
+            "snapshot_dir",
+            "must not be empty",
+        );
+        check(
+            self.snapshot_keep == 0,
+            "snapshot_keep",
+            "must be at least 1",
+        );
+
+        for (key, schedule) in [
+            ("bank_refresh_schedule", &self.bank_refresh_schedule),
+            ("vote_sync_schedule", &self.vote_sync_schedule),
+            ("cleanup_schedule", &self.cleanup_schedule),
+            ("snapshot_schedule", &self.snapshot_schedule),
+        ] {
+            check(
+                !is_schedule(schedule),
+                key,
+                "must be a cron expression with seconds, e.g. \"0 0 4 * * *\"",
+            );
         }
     }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap_or_else(|e| format!("Failed to print config: {e}"))
+    }
 }
 
-fn var(key: &str) -> Result<String, ()> {
-    env::var(key).map_err(|_| {
-        warn!("Environment variable {key} not found, using default");
-    })
+// the printed TOML, so secrets stay redacted wherever the config ends up in a log
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_toml())
+    }
 }
 
-fn try_load<T: FromStr>(key: &str, default: &str) -> T
-where
-    T::Err: Display,
-{
-    var(key)
-        .unwrap_or_else(|_| {
-            info!("{key} not set, using default: {default}");
-            default.to_string()
-        })
-        .parse()
-        .map_err(|e| {
-            warn!("Invalid {key} value: {e}");
-        })
-        .expect("Environment misconfigured!")
+struct Loader {
+    file: Table,
+    // environment variables and secrets on top of the file
+    layered: bool,
+    used: HashSet<&'static str>,
+    errors: Vec<ConfigError>,
 }
 
-fn read_secret(secret_name: &str) -> String {
-    let path = format!("/run/secrets/{secret_name}");
+impl Loader {
+    fn new() -> Self {
+        let path = env::var(CONFIG_PATH).unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+        let mut errors = Vec::new();
+
+        let file = match read_to_string(&path) {
+            Ok(contents) => contents.parse::<Table>().unwrap_or_else(|e| {
+                errors.push(ConfigError::File {
+                    path: path.clone(),
+                    reason: e.to_string(),
+                });
+                Table::new()
+            }),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                info!("No config file at {path}, using defaults and environment");
+                Table::new()
+            }
+            Err(e) => {
+                errors.push(ConfigError::File {
+                    path: path.clone(),
+                    reason: e.to_string(),
+                });
+                Table::new()
+            }
+        };
+
+        Self {
+            file,
+            layered: true,
+            used: HashSet::new(),
+            errors,
+        }
+    }
+
+    fn raw(&mut self, key: &'static str, env_key: &str) -> Option<String> {
+        self.used.insert(key);
+
+        self.layered
+            .then(|| env::var(env_key).ok())
+            .flatten()
+            .or_else(|| self.file.get(key).map(value_to_string))
+    }
+
+    fn parse<T: FromStr>(&mut self, key: &'static str, value: String) -> Option<T>
+    where
+        T::Err: Display,
+    {
+        value
+            .parse()
+            .map_err(|e: T::Err| {
+                self.errors.push(ConfigError::Parse {
+                    key,
+                    value,
+                    reason: e.to_string(),
+                });
+            })
+            .ok()
+    }
+
+    fn load<T: FromStr + Default>(&mut self, key: &'static str, env_key: &str, default: &str) -> T
+    where
+        T::Err: Display,
+    {
+        let value = self
+            .raw(key, env_key)
+            .unwrap_or_else(|| default.to_string());
+
+        // placeholder on error, the collected errors stop the server before it is used
+        self.parse(key, value).unwrap_or_default()
+    }
+
+    fn optional<T: FromStr>(&mut self, key: &'static str, env_key: &str) -> Option<T>
+    where
+        T::Err: Display,
+    {
+        let value = self.raw(key, env_key).filter(|value| !value.is_empty())?;
+
+        self.parse(key, value)
+    }
+
+    fn optional_secret(&mut self, key: &'static str, env_key: &'static str) -> Option<String> {
+        let path = format!("{SECRETS_DIR}/{env_key}");
+
+        self.used.insert(key);
 
-    read_to_string(&path)
-        .map(|s| s.trim().to_string())
-        .map_err(|e| {
-            warn!("Failed to read {secret_name} from file: {e}");
+        self.layered
+            .then(|| read_to_string(&path).ok())
+            .flatten()
+            .map(|s| s.trim().to_string())
+            .or_else(|| self.raw(key, env_key))
+            .filter(|secret| !secret.is_empty())
+    }
+
+    fn secret(&mut self, key: &'static str, env_key: &'static str) -> String {
+        self.optional_secret(key, env_key).unwrap_or_else(|| {
+            self.errors.push(ConfigError::Missing { key, env: env_key });
+            String::new()
         })
-        .expect("Secrets misconfigured!")
+    }
+
+    fn reject_unknown(&mut self) {
+        let unknown: Vec<String> = self
+            .file
+            .keys()
+            .filter(|key| !self.used.contains(key.as_str()))
+            .cloned()
+            .collect();
+
+        self.errors
+            .extend(unknown.into_iter().map(|key| ConfigError::Unknown { key }));
+    }
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn is_url(url: &str, schemes: &[&str]) -> bool {
+    schemes
+        .iter()
+        .any(|scheme| url.len() > scheme.len() && url.starts_with(scheme))
+}
+
+fn is_schedule(schedule: &str) -> bool {
+    Job::new(schedule, |_, _| {}).is_ok()
+}
+
+fn redact<S: Serializer>(_: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(REDACTED)
+}
+
+fn redact_option<S: Serializer>(secret: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    match secret {
+        Some(_) => serializer.serialize_str(REDACTED),
+        None => serializer.serialize_none(),
+    }
 }
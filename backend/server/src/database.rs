@@ -19,39 +19,65 @@
 //! - For foods: string + 32-bit **votes** int
 //! - Atomic operations, Redis loads operations into a queue
 //! - Estimated memory usage:
-//! (32 bytes (bitmap) + 20 bytes (key overhead)) × 50,000 = roughly 2.6 MB
-use std::{collections::HashMap, time::Duration};
+//!   (32 bytes (bitmap) + 20 bytes (key overhead)) × 50,000 = roughly 2.6 MB
+//!
+//! ## Vote Buckets
+//!
+//! - Redis hash per hour `votes:hour:<hours since epoch>`: food id to vote delta within that hour
+//! - Written alongside the running total so windowed views (trending) can sum recent deltas
+//! - Sorted set `votes:hours` indexes which hourly buckets exist, scored by hour
+//! - Redis hash per day `votes:day:<days since epoch>`: hourly buckets rolled up by the compaction job
+//!   once older than the hourly retention, kept indefinitely
+//! - ~2000 foods × 365 days stays within a few MB even if every food gets a vote every day
+//!
+//! ## Users
+//!
+//! - Redis hash `users`: `valid_id` to the latest bitmap that user submitted
+//! - Written after their votes are applied, only read by the admin API
+//!
+//! ## Audit
+//!
+//! - Redis list `audit`: admin changes as JSON, newest first, trimmed to the last 1000
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use once_cell::sync::Lazy;
 use redis::{
-    Client, Script,
+    AsyncCommands, Client, RedisError, Script,
     aio::{ConnectionManager, ConnectionManagerConfig},
+    pipe,
 };
+use serde::Deserialize;
+use tracing::instrument;
 
-use crate::error::AppError::{self, InternalError};
+use crate::{
+    error::AppError::{self, InternalError, NotFound},
+    monitor::record_redis,
+};
 
 const FOODS_HASH: &str = "foods";
+const HOURLY_PREFIX: &str = "votes:hour";
+const HOURLY_INDEX: &str = "votes:hours";
+const DAILY_PREFIX: &str = "votes:day";
+pub const HOURS_PER_DAY: u64 = 24;
+pub const MAX_WINDOW_HOURS: u64 = 7 * 24;
+pub const RATE_LIMIT_PREFIX: &str = "limit";
+const USERS_HASH: &str = "users";
+const AUDIT_LIST: &str = "audit";
+pub const AUDIT_LENGTH: usize = 1000;
 
-pub async fn init_redis(
-    redis_url: &str,
-    food_id_to_name: &Vec<String>,
-) -> (ConnectionManager, HashMap<String, u32>) {
+pub async fn connect_redis(redis_url: &str) -> Result<ConnectionManager, RedisError> {
     let config = ConnectionManagerConfig::new()
         .set_number_of_retries(1)
         .set_connection_timeout(Some(Duration::from_millis(100)));
 
-    let client = Client::open(redis_url).unwrap();
-    let mut connection_manager = client
-        .get_connection_manager_with_config(config)
-        .await
-        .unwrap();
-
-    let food_votes = populate_foods(food_id_to_name, &mut connection_manager).await;
-
-    (connection_manager, food_votes)
+    let client = Client::open(redis_url)?;
+    client.get_connection_manager_with_config(config).await
 }
 
-fn map_indices_to_zero(id_to_string: &Vec<String>) -> Vec<usize> {
+fn map_indices_to_zero(id_to_string: &[String]) -> Vec<usize> {
     id_to_string
         .iter()
         .enumerate()
@@ -84,38 +110,57 @@ static POPULATE_FOODS_SCRIPT: Lazy<Script> = Lazy::new(|| {
 });
 
 pub async fn populate_foods(
-    food_id_to_name: &Vec<String>,
+    food_id_to_name: &[String],
     connection_manager: &mut ConnectionManager,
-) -> HashMap<String, u32> {
+) -> Result<HashMap<u32, i64>, RedisError> {
     // using a script instead of hset_multiple to avoid overwriting existing values
-    let food_votes_vector: Vec<String> = POPULATE_FOODS_SCRIPT
+    let started = Instant::now();
+    let result = POPULATE_FOODS_SCRIPT
         .key(FOODS_HASH)
         .arg(map_indices_to_zero(food_id_to_name))
         .invoke_async(connection_manager)
-        .await
-        .unwrap();
+        .await;
+    record_redis("populate_foods", started, &result);
+    let food_votes_vector: Vec<String> = result?;
 
-    food_votes_vector
+    Ok(food_votes_vector
         .chunks(2)
-        .map(|c| (c[0].clone(), c[1].parse::<u32>().unwrap()))
-        .collect()
+        .map(|c| (c[0].parse::<u32>().unwrap(), c[1].parse::<i64>().unwrap()))
+        .collect())
 }
 
 static UPDATE_FOODS_SCRIPT: Lazy<Script> = Lazy::new(|| {
     Script::new(
         r#"
         local hash = KEYS[1]
+        local bucket = KEYS[2]
+        local index = KEYS[3]
+        local hour = ARGV[1]
+        local changed = false
 
-        for i = 1, #ARGV, 2 do
+        -- an unknown food rejects the whole batch before anything is written
+        for i = 2, #ARGV, 2 do
+            if redis.call("HEXISTS", hash, ARGV[i]) == 0 then
+                return ARGV[i]
+            end
+        end
+
+        for i = 2, #ARGV, 2 do
             local food_key = ARGV[i]
             local amount = tonumber(ARGV[i + 1])
-            
+
             local current = tonumber(redis.call("HGET", hash, food_key))
-            
+
             if current + amount >= 0 then
                 redis.call("HINCRBY", hash, food_key, amount)
+                redis.call("HINCRBY", bucket, food_key, amount)
+                changed = true
             end
         end
+
+        if changed then
+            redis.call("ZADD", index, hour, hour)
+        end
         "#,
     )
 });
@@ -135,6 +180,7 @@ impl Vote {
     }
 }
 
+#[instrument(skip_all, fields(votes = votes.len()))]
 pub async fn update_foods(
     connection_manager: &mut ConnectionManager,
     votes: &[(isize, Vote)],
@@ -148,12 +194,417 @@ pub async fn update_foods(
         arguments.extend([*food_key, vote.as_str()]);
     }
 
-    let _: () = UPDATE_FOODS_SCRIPT
+    let hour = current_hour();
+
+    let started = Instant::now();
+    let result: Result<Option<u32>, _> = UPDATE_FOODS_SCRIPT
         .key(FOODS_HASH)
+        .key(hourly_key(hour))
+        .key(HOURLY_INDEX)
+        .arg(hour)
         .arg(arguments)
         .invoke_async(connection_manager)
+        .await;
+    record_redis("update_foods", started, &result);
+
+    match result.map_err(|e| InternalError(Box::new(e)))? {
+        Some(food_id) => Err(NotFound(format!("votes for food {food_id}"))),
+        None => Ok(()),
+    }
+}
+
+pub fn current_hour() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / (60 * 60))
+        .unwrap_or_default()
+}
+
+fn hourly_key(hour: u64) -> String {
+    format!("{HOURLY_PREFIX}:{hour}")
+}
+
+fn daily_key(day: u64) -> String {
+    format!("{DAILY_PREFIX}:{day}")
+}
+
+pub async fn get_food_votes(
+    connection_manager: &mut ConnectionManager,
+) -> Result<HashMap<u32, i64>, AppError> {
+    connection_manager
+        .hgetall(FOODS_HASH)
+        .await
+        .map_err(|e| InternalError(Box::new(e)))
+}
+
+// sums the hourly buckets of the last `hours` hours, including the current one
+pub async fn get_vote_deltas(
+    connection_manager: &mut ConnectionManager,
+    hours: u64,
+) -> Result<HashMap<u32, i64>, AppError> {
+    let now = current_hour();
+
+    let mut pipeline = pipe();
+    for hour in now.saturating_sub(hours.saturating_sub(1))..=now {
+        pipeline.hgetall(hourly_key(hour));
+    }
+
+    let buckets: Vec<HashMap<u32, i64>> = pipeline
+        .query_async(connection_manager)
+        .await
+        .map_err(|e| InternalError(Box::new(e)))?;
+
+    let mut deltas = HashMap::new();
+    for bucket in buckets {
+        for (food_id, delta) in bucket {
+            *deltas.entry(food_id).or_insert(0) += delta;
+        }
+    }
+
+    Ok(deltas)
+}
+
+// (hours since epoch, vote delta) for every hour in range, oldest first, only while not yet compacted
+pub async fn get_hourly_history(
+    connection_manager: &mut ConnectionManager,
+    food_id: u32,
+    hours: u64,
+) -> Result<Vec<(u64, i64)>, AppError> {
+    let now = current_hour();
+    let hours: Vec<u64> = (now.saturating_sub(hours.saturating_sub(1))..=now).collect();
+
+    let mut pipeline = pipe();
+    for hour in &hours {
+        pipeline.hget(hourly_key(*hour), food_id);
+    }
+
+    let deltas: Vec<Option<i64>> = pipeline
+        .query_async(connection_manager)
+        .await
+        .map_err(|e| InternalError(Box::new(e)))?;
+
+    Ok(hours
+        .into_iter()
+        .zip(deltas)
+        .map(|(hour, delta)| (hour, delta.unwrap_or(0)))
+        .collect())
+}
+
+// (days since epoch, vote delta) for every day in range, oldest first, merging uncompacted hours
+pub async fn get_daily_history(
+    connection_manager: &mut ConnectionManager,
+    food_id: u32,
+    days: u64,
+) -> Result<Vec<(u64, i64)>, AppError> {
+    let today = current_hour() / HOURS_PER_DAY;
+    let first_day = today.saturating_sub(days.saturating_sub(1));
+
+    let hours: Vec<u64> = connection_manager
+        .zrangebyscore(HOURLY_INDEX, first_day * HOURS_PER_DAY, "+inf")
+        .await
+        .map_err(|e| InternalError(Box::new(e)))?;
+
+    let mut pipeline = pipe();
+    for day in first_day..=today {
+        pipeline.hget(daily_key(day), food_id);
+    }
+    for hour in &hours {
+        pipeline.hget(hourly_key(*hour), food_id);
+    }
+
+    let deltas: Vec<Option<i64>> = pipeline
+        .query_async(connection_manager)
+        .await
+        .map_err(|e| InternalError(Box::new(e)))?;
+
+    let mut history: Vec<(u64, i64)> = (first_day..=today)
+        .zip(&deltas)
+        .map(|(day, delta)| (day, delta.unwrap_or(0)))
+        .collect();
+
+    for (hour, delta) in hours.iter().zip(&deltas[history.len()..]) {
+        let index = (hour / HOURS_PER_DAY - first_day) as usize;
+        if let Some((_, total)) = history.get_mut(index) {
+            *total += delta.unwrap_or(0);
+        }
+    }
+
+    Ok(history)
+}
+
+static COMPACT_HOUR_SCRIPT: Lazy<Script> = Lazy::new(|| {
+    Script::new(
+        r#"
+        local hourly = KEYS[1]
+        local daily = KEYS[2]
+        local index = KEYS[3]
+        local deltas = redis.call("HGETALL", hourly)
+
+        for i = 1, #deltas, 2 do
+            redis.call("HINCRBY", daily, deltas[i], deltas[i + 1])
+        end
+
+        redis.call("DEL", hourly)
+        redis.call("ZREM", index, ARGV[1])
+        "#,
+    )
+});
+
+// rolls hourly buckets older than the retention into their daily bucket, returns hours compacted
+#[instrument(skip(connection_manager))]
+pub async fn compact_votes(
+    connection_manager: &mut ConnectionManager,
+    retention_hours: u64,
+) -> Result<usize, AppError> {
+    let cutoff = current_hour().saturating_sub(retention_hours);
+
+    let hours: Vec<u64> = connection_manager
+        .zrangebyscore(HOURLY_INDEX, "-inf", format!("({cutoff}"))
+        .await
+        .map_err(|e| InternalError(Box::new(e)))?;
+
+    for hour in &hours {
+        let started = Instant::now();
+        let result: Result<(), _> = COMPACT_HOUR_SCRIPT
+            .key(hourly_key(*hour))
+            .key(daily_key(hour / HOURS_PER_DAY))
+            .key(HOURLY_INDEX)
+            .arg(hour)
+            .invoke_async(connection_manager)
+            .await;
+        record_redis("compact_hour", started, &result);
+        result.map_err(|e| InternalError(Box::new(e)))?;
+    }
+
+    Ok(hours.len())
+}
+
+static TAKE_TOKENS_SCRIPT: Lazy<Script> = Lazy::new(|| {
+    Script::new(
+        r#"
+        local time = redis.call("TIME")
+        local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+        local retry_after = 0
+        local buckets = {}
+
+        for i = 1, #KEYS do
+            local burst = tonumber(ARGV[i * 2 - 1])
+            local refill = tonumber(ARGV[i * 2])
+            local bucket = redis.call("HMGET", KEYS[i], "tokens", "updated")
+
+            local tokens = tonumber(bucket[1]) or burst
+            local updated = tonumber(bucket[2]) or now
+            tokens = math.min(burst, tokens + (now - updated) / refill)
+
+            if tokens < 1 then
+                retry_after = math.max(retry_after, math.ceil((1 - tokens) * refill))
+            end
+
+            buckets[i] = tokens
+        end
+
+        if retry_after > 0 then
+            return retry_after
+        end
+
+        for i = 1, #KEYS do
+            local burst = tonumber(ARGV[i * 2 - 1])
+            local refill = tonumber(ARGV[i * 2])
+
+            redis.call("HSET", KEYS[i], "tokens", buckets[i] - 1, "updated", now)
+            redis.call("PEXPIRE", KEYS[i], math.ceil(burst * refill))
+        end
+
+        return 0
+        "#,
+    )
+});
+
+pub struct Bucket {
+    pub key: String,
+    pub burst: u32,
+    pub refill_ms: u64,
+}
+
+// takes one token from every bucket or none at all, returning ms to wait (0 if allowed)
+pub async fn take_tokens(
+    connection_manager: &mut ConnectionManager,
+    buckets: &[Bucket],
+) -> Result<u64, AppError> {
+    if buckets.is_empty() {
+        return Ok(0);
+    }
+
+    let mut script = TAKE_TOKENS_SCRIPT.prepare_invoke();
+    for bucket in buckets {
+        script
+            .key(&bucket.key)
+            .arg(bucket.burst)
+            .arg(bucket.refill_ms);
+    }
+
+    let started = Instant::now();
+    let result = script.invoke_async(connection_manager).await;
+    record_redis("take_tokens", started, &result);
+
+    result.map_err(|e| InternalError(Box::new(e)))
+}
+
+pub async fn store_user_votes(
+    connection_manager: &mut ConnectionManager,
+    user_id: &str,
+    bit_map: &[u8],
+) -> Result<(), AppError> {
+    connection_manager
+        .hset(USERS_HASH, user_id, bit_map)
+        .await
+        .map_err(|e| InternalError(Box::new(e)))
+}
+
+pub struct VoteState {
+    pub foods: HashMap<u32, i64>,
+    pub users: HashMap<String, Vec<u8>>,
+}
+
+// food totals and user bitmaps at the same instant
+pub async fn get_vote_state(
+    connection_manager: &mut ConnectionManager,
+) -> Result<VoteState, AppError> {
+    let (foods, users) = pipe()
+        .atomic()
+        .hgetall(FOODS_HASH)
+        .hgetall(USERS_HASH)
+        .query_async(connection_manager)
         .await
         .map_err(|e| InternalError(Box::new(e)))?;
 
-    Ok(())
+    Ok(VoteState { foods, users })
+}
+
+pub async fn restore_vote_state(
+    connection_manager: &mut ConnectionManager,
+    vote_state: &VoteState,
+) -> Result<(), AppError> {
+    let mut pipeline = pipe();
+    pipeline.atomic();
+
+    // HSET needs at least one field
+    if !vote_state.foods.is_empty() {
+        let foods: Vec<(u32, i64)> = vote_state.foods.iter().map(|(k, v)| (*k, *v)).collect();
+        pipeline.hset_multiple(FOODS_HASH, &foods).ignore();
+    }
+    if !vote_state.users.is_empty() {
+        let users: Vec<(&str, &[u8])> = vote_state
+            .users
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_slice()))
+            .collect();
+        pipeline.hset_multiple(USERS_HASH, &users).ignore();
+    }
+
+    pipeline
+        .query_async(connection_manager)
+        .await
+        .map_err(|e| InternalError(Box::new(e)))
+}
+
+pub async fn get_user_votes(
+    connection_manager: &mut ConnectionManager,
+    user_id: &str,
+) -> Result<Option<Vec<u8>>, AppError> {
+    connection_manager
+        .hget(USERS_HASH, user_id)
+        .await
+        .map_err(|e| InternalError(Box::new(e)))
+}
+
+pub async fn get_votes(
+    connection_manager: &mut ConnectionManager,
+    food_id: u32,
+) -> Result<Option<i64>, AppError> {
+    connection_manager
+        .hget(FOODS_HASH, food_id)
+        .await
+        .map_err(|e| InternalError(Box::new(e)))
+}
+
+static CHANGE_VOTES_SCRIPT: Lazy<Script> = Lazy::new(|| {
+    Script::new(
+        r#"
+        local current = redis.call("HGET", KEYS[1], ARGV[1])
+
+        if not current then
+            return nil
+        end
+
+        current = tonumber(current)
+        local votes = tonumber(ARGV[3])
+
+        if ARGV[2] == "adjust" then
+            votes = current + votes
+        end
+
+        votes = math.max(votes, 0)
+        redis.call("HSET", KEYS[1], ARGV[1], votes)
+
+        return {current, votes}
+        "#,
+    )
+});
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteChange {
+    Set(i64),
+    Adjust(i64),
+}
+
+// only touches the running total, corrections should not show up as trending
+// returns (before, after), None if the food has no votes entry
+pub async fn change_votes(
+    connection_manager: &mut ConnectionManager,
+    food_id: u32,
+    change: VoteChange,
+) -> Result<Option<(i64, i64)>, AppError> {
+    let (mode, amount) = match change {
+        VoteChange::Set(votes) => ("set", votes),
+        VoteChange::Adjust(delta) => ("adjust", delta),
+    };
+
+    let started = Instant::now();
+    let result = CHANGE_VOTES_SCRIPT
+        .key(FOODS_HASH)
+        .arg(food_id)
+        .arg(mode)
+        .arg(amount)
+        .invoke_async(connection_manager)
+        .await;
+    record_redis("change_votes", started, &result);
+
+    result.map_err(|e| InternalError(Box::new(e)))
+}
+
+pub async fn push_audit(
+    connection_manager: &mut ConnectionManager,
+    entry: &str,
+) -> Result<(), AppError> {
+    pipe()
+        .atomic()
+        .lpush(AUDIT_LIST, entry)
+        .ignore()
+        .ltrim(AUDIT_LIST, 0, AUDIT_LENGTH as isize - 1)
+        .ignore()
+        .query_async(connection_manager)
+        .await
+        .map_err(|e| InternalError(Box::new(e)))
+}
+
+pub async fn get_audit(
+    connection_manager: &mut ConnectionManager,
+    count: usize,
+) -> Result<Vec<String>, AppError> {
+    connection_manager
+        .lrange(AUDIT_LIST, 0, count as isize - 1)
+        .await
+        .map_err(|e| InternalError(Box::new(e)))
 }
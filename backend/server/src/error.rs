@@ -1,25 +1,118 @@
 use axum::{
-    http::StatusCode,
+    http::{StatusCode, header::RETRY_AFTER},
     response::{IntoResponse, Response},
 };
+use bank::payloads::Error as ErrorMessage;
 use thiserror::Error;
 
+// next_food_id of the server's bank, sent along with UnknownBank
+pub const BANK_VERSION_HEADER: &str = "x-bank-version";
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Malformed payload")]
     MalformedPayload,
 
+    #[error("Invalid parameter: {0}")]
+    InvalidParameter(String),
+
+    // flips, cap, seconds until the IP bucket refills a token
+    #[error("Too many votes: {0} exceeds {1}")]
+    TooManyVotes(usize, usize, u64),
+
+    #[error("Unknown bank {client}, the server has {server}, re-fetch /bank")]
+    UnknownBank { client: u32, server: u32 },
+
+    #[error("Rate limited, retry after {0}s")]
+    RateLimited(u64),
+
+    #[error("Invalid token")]
+    InvalidToken,
+
+    #[error("Expired token")]
+    ExpiredToken,
+
+    #[error("Request too soon after the previous one")]
+    Debounced,
+
+    #[error("Missing or invalid admin key")]
+    Unauthorized,
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(&'static str),
+
     #[error("Internal error: {0}")]
     InternalError(#[from] Box<dyn std::error::Error + Send + Sync>),
 }
 
+impl AppError {
+    // stable across releases, clients match on it rather than the message
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::MalformedPayload => "malformed_payload",
+            AppError::InvalidParameter { .. } => "invalid_parameter",
+            AppError::TooManyVotes { .. } => "too_many_votes",
+            AppError::UnknownBank { .. } => "unknown_bank",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::InvalidToken => "invalid_token",
+            AppError::ExpiredToken => "expired_token",
+            AppError::Debounced => "debounced",
+            AppError::Unauthorized => "unauthorized",
+            AppError::NotFound { .. } => "not_found",
+            AppError::Conflict { .. } => "conflict",
+            AppError::Unavailable { .. } => "unavailable",
+            AppError::InternalError { .. } => "internal",
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let status = match self {
-            AppError::MalformedPayload { .. } => StatusCode::BAD_REQUEST,
+            AppError::MalformedPayload | AppError::InvalidParameter { .. } => {
+                StatusCode::BAD_REQUEST
+            }
+            AppError::TooManyVotes { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::UnknownBank { .. } => StatusCode::CONFLICT,
+            AppError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::InvalidToken | AppError::ExpiredToken => StatusCode::UNAUTHORIZED,
+            AppError::Debounced => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::NotFound { .. } => StatusCode::NOT_FOUND,
+            AppError::Conflict { .. } => StatusCode::CONFLICT,
+            AppError::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
             AppError::InternalError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
         };
+        let message = self.to_string();
+
+        let mut response = match self {
+            AppError::RateLimited(retry_after) | AppError::TooManyVotes(_, _, retry_after) => (
+                status,
+                [(RETRY_AFTER, retry_after.to_string())],
+                message.clone(),
+            )
+                .into_response(),
+            AppError::UnknownBank { server, .. } => (
+                status,
+                [(BANK_VERSION_HEADER, server.to_string())],
+                message.clone(),
+            )
+                .into_response(),
+            _ => (status, message.clone()).into_response(),
+        };
+
+        // plain text unless the client asked for a format, see crate::negotiate
+        response.extensions_mut().insert(ErrorMessage {
+            code: self.code().to_string(),
+            message,
+        });
 
-        (status, self.to_string()).into_response()
+        response
     }
 }
@@ -0,0 +1,233 @@
+//! # Search Fallback
+//!
+//! In-process matcher over the loaded bank, serving `/search` while Meilisearch is down or still indexing.
+//!
+//! ## Matching
+//! - Query and names are folded to lowercase the same way and split into words, each query word is scored against every word of a name
+//! - Exact word beats prefix beats trigram similarity, trigrams tolerate typos like `chiken`
+//! - Words shorter than the query's `typo_min` only match exactly
+//! - `all` needs every query word to match, `last` and `frequency` need one, foods matching more words rank first
+//! - Ties go to the shorter name, then the lower id
+//!
+//! ## Degraded
+//! - Filters, facets, paging, sorts and highlights follow [`FoodQuery`] like Meilisearch does
+//! - No synonyms or stop words, and votes are only fetched for a `votes` sort
+//! - ~2000 foods per the bank estimate, scanned per request without an index
+use std::collections::HashMap;
+
+use bank::{RemoteBank, payloads::Highlight};
+use meilisearch_sdk::search::MatchingStrategies;
+
+use crate::search::{
+    FOOD_NAME, FOOD_VOTES, Facets, FoodQuery, FoodResults, MeiliFood, to_meili_foods,
+};
+
+// share of trigrams two words need in common, as in pg_trgm
+const SIMILARITY: f32 = 0.3;
+
+const EXACT: f32 = 3.0;
+const PREFIX: f32 = 2.0;
+
+struct Scored<'a> {
+    food: &'a MeiliFood,
+    matched: usize,
+    score: f32,
+    // byte ranges of the matched words in the name
+    ranges: Vec<(usize, usize)>,
+}
+
+// true when the query sorts by votes, the only time the fallback needs them
+pub fn needs_votes(query: &FoodQuery) -> bool {
+    query
+        .sort
+        .is_some_and(|sort| sort.split(':').next() == Some(FOOD_VOTES))
+}
+
+pub fn search_bank(
+    remote_bank: &RemoteBank,
+    food_votes: &HashMap<u32, i64>,
+    query: &FoodQuery,
+) -> FoodResults {
+    let foods = to_meili_foods(&remote_bank.bank, food_votes);
+    let words = words(&query.text);
+    let every_word = matches!(query.matching, Some(MatchingStrategies::ALL));
+
+    let mut scored: Vec<Scored> = foods
+        .iter()
+        .filter(|food| query.matches(food))
+        .filter_map(|food| score(&words, food, every_word, query.typo_min))
+        .collect();
+    // placeholder searches keep id order, like documents in Meilisearch
+    scored.sort_by(|a, b| {
+        b.matched
+            .cmp(&a.matched)
+            .then(b.score.total_cmp(&a.score))
+            .then_with(|| match words.is_empty() {
+                true => a.food.id.cmp(&b.food.id),
+                false => (a.food.name.len(), a.food.id).cmp(&(b.food.name.len(), b.food.id)),
+            })
+    });
+
+    let mut matches: Vec<&MeiliFood> = scored.iter().map(|scored| scored.food).collect();
+    query.order(&mut matches);
+
+    let page: Vec<&MeiliFood> = matches
+        .iter()
+        .skip(query.offset)
+        .take(query.limit)
+        .copied()
+        .collect();
+
+    let mut highlights = Vec::new();
+    if query.highlight.contains(&FOOD_NAME) {
+        let ranges: HashMap<u32, &[(usize, usize)]> = scored
+            .iter()
+            .map(|scored| (scored.food.id, scored.ranges.as_slice()))
+            .collect();
+
+        for food in &page {
+            highlights.extend(ranges[&food.id].iter().map(|&(start, length)| Highlight {
+                id: food.id,
+                attribute: FOOD_NAME.to_string(),
+                start: start as u32,
+                length: length as u32,
+            }));
+        }
+    }
+
+    FoodResults {
+        ids: page.iter().map(|food| food.id).collect(),
+        total: matches.len(),
+        facets: Facets::count(matches),
+        highlights,
+    }
+}
+
+// None when the food does not match, every food matches an empty query
+// words shorter than typo_min only match exactly, like the quoted words Meilisearch gets
+fn score<'a>(
+    words: &[String],
+    food: &'a MeiliFood,
+    every_word: bool,
+    typo_min: usize,
+) -> Option<Scored<'a>> {
+    let name = fold(&food.name);
+    let name_words = word_ranges(&name);
+
+    let mut scored = Scored {
+        food,
+        matched: 0,
+        score: 0.0,
+        ranges: Vec::new(),
+    };
+
+    for word in words {
+        let exact_only = word.chars().count() < typo_min;
+        let best = name_words
+            .iter()
+            .filter_map(|&(start, name_word)| {
+                let (score, length) = if name_word == word {
+                    (EXACT, name_word.len())
+                } else if exact_only {
+                    return None;
+                } else if name_word.starts_with(word.as_str()) {
+                    (PREFIX, word.len())
+                } else {
+                    let similarity = similarity(word, name_word);
+                    if similarity < SIMILARITY {
+                        return None;
+                    }
+                    (similarity, name_word.len())
+                };
+
+                Some((score, start, length))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        match best {
+            Some((score, start, length)) => {
+                scored.matched += 1;
+                scored.score += score;
+                scored.ranges.push((start, length));
+            }
+            None if every_word => return None,
+            None => {}
+        }
+    }
+
+    (words.is_empty() || scored.matched > 0).then(|| {
+        scored.ranges.sort();
+        scored.ranges.dedup();
+        scored
+    })
+}
+
+fn words(text: &str) -> Vec<String> {
+    fold(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// lowercase for queries and names alike, chars whose lowercase is longer stay as they are
+// so highlight byte offsets into the folded name still fit the original
+fn fold(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            let mut lower = c.to_lowercase();
+            match (lower.next(), lower.next()) {
+                (Some(lower), None) if lower.len_utf8() == c.len_utf8() => lower,
+                _ => c,
+            }
+        })
+        .collect()
+}
+
+// words of a lowercase name with their byte offsets
+fn word_ranges(name: &str) -> Vec<(usize, &str)> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+
+    for (index, c) in name.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(begin)) => {
+                ranges.push((begin, &name[begin..index]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        ranges.push((begin, &name[begin..]));
+    }
+
+    ranges
+}
+
+// shared trigrams over every distinct trigram, words padded like pg_trgm
+fn similarity(a: &str, b: &str) -> f32 {
+    let (a, b) = (trigrams(a), trigrams(b));
+    let shared = a.iter().filter(|trigram| b.contains(trigram)).count();
+    let union = a.len() + b.len() - shared;
+
+    if union == 0 {
+        return 0.0;
+    }
+
+    shared as f32 / union as f32
+}
+
+fn trigrams(word: &str) -> Vec<[char; 3]> {
+    let padded: Vec<char> = "  ".chars().chain(word.chars()).chain([' ']).collect();
+
+    let mut trigrams: Vec<[char; 3]> = padded
+        .windows(3)
+        .map(|window| [window[0], window[1], window[2]])
+        .collect();
+    trigrams.sort();
+    trigrams.dedup();
+
+    trigrams
+}
@@ -0,0 +1,133 @@
+//! # Health
+//!
+//! Probes for Docker swarm and the reverse proxy.
+//!
+//! ## Endpoints
+//! - `/healthz`: liveness, 200 as long as the process serves requests
+//! - `/readyz`: readiness, pings the vote store, checks the search backend's health and reports the loaded bank
+//!
+//! ## Readiness
+//! - 200 when every dependency is up, 503 otherwise
+//! - JSON breakdown either way, each dependency with `ok` and the error if any
+//! - Bank is reported with its age since fetched and its food count, not ready if it has no foods
+//! - The vote store and search are down until they connect, with the last connect error while reconnecting,
+//!   see [`crate::state`]
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use serde::Serialize;
+use tokio::time::{error::Elapsed, timeout};
+
+use crate::state::State as AppState;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+pub struct Dependency {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BankStatus {
+    pub ok: bool,
+    pub age_seconds: u64,
+    pub foods: usize,
+    pub next_food_id: u32,
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+    pub store: Dependency,
+    pub search: Dependency,
+    pub bank: BankStatus,
+}
+
+impl Readiness {
+    pub fn is_ready(&self) -> bool {
+        self.store.ok && self.search.ok && self.bank.ok
+    }
+}
+
+impl Dependency {
+    fn down(error: &str) -> Self {
+        Self {
+            ok: false,
+            error: Some(error.to_string()),
+        }
+    }
+
+    // the last connect error, or `pending` before the first attempt failed
+    fn connecting(last_error: &Option<Arc<String>>, pending: &str) -> Self {
+        Self::down(last_error.as_deref().map_or(pending, String::as_str))
+    }
+
+    fn from_result<T, E: ToString>(result: Result<Result<T, E>, Elapsed>) -> Self {
+        let error = match result {
+            Ok(Ok(_)) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(_) => Some("Timed out".to_string()),
+        };
+
+        Self {
+            ok: error.is_none(),
+            error,
+        }
+    }
+}
+
+pub async fn healthz_handler() -> impl IntoResponse {
+    (StatusCode::OK, "OK")
+}
+
+pub async fn readyz_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let readiness = get_readiness(state).await;
+
+    let status = if readiness.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(readiness))
+}
+
+pub async fn get_readiness(state: Arc<AppState>) -> Readiness {
+    let store_ping = async {
+        let Ok(store) = state.store() else {
+            return Dependency::connecting(&state.store_error.load(), "Not connected yet");
+        };
+
+        Dependency::from_result(timeout(PROBE_TIMEOUT, store.ping()).await)
+    };
+    let search_health = async {
+        let Ok(search) = state.search() else {
+            return Dependency::connecting(&state.search_error.load(), "Not indexed yet");
+        };
+
+        Dependency::from_result(timeout(PROBE_TIMEOUT, search.health()).await)
+    };
+
+    let (store, search) = tokio::join!(store_ping, search_health);
+
+    let remote_bank = state.remote_bank.load();
+    let foods = remote_bank.bank.foods.len();
+
+    Readiness {
+        store,
+        search,
+        bank: BankStatus {
+            ok: foods > 0,
+            age_seconds: SystemTime::now()
+                .duration_since(remote_bank.fetched_at)
+                .map(|age| age.as_secs())
+                .unwrap_or_default(),
+            foods,
+            next_food_id: remote_bank.bank.next_food_id,
+        },
+    }
+}
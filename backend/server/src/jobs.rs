@@ -0,0 +1,356 @@
+//! # Jobs
+//!
+//! Cron jobs and a registry tracking every run.
+//!
+//! ## Jobs
+//! - `bank_refresh`: refetches the remote bank, adds its new foods to the vote store before swapping it in,
+//!   then upserts it into search, `BANK_REFRESH_SCHEDULE`, daily at 4am by default
+//! - `vote_sync`: copies the Redis vote counts into Meilisearch, `VOTE_SYNC_SCHEDULE`, every 5 minutes by default
+//! - `cleanup`: compacts hourly vote buckets past the retention into days, `CLEANUP_SCHEDULE`, hourly by default
+//! - `snapshot`: saves the votes to disk, `SNAPSHOT_SCHEDULE`, hourly by default, see [`crate::snapshot`]
+//!
+//! ## Registry
+//! - Records the last run of every job with its duration and outcome, plus the next scheduled run
+//! - A run is skipped while the previous one is still going, a manual trigger gets a 409 instead
+//! - The running flag is cleared by a guard, so a panicking run does not block the job forever
+//! - Failures are logged at warn and counted in `cron_job_runs_total`
+//!
+//! ## Admin
+//!
+//! List jobs.
+//! ```sh
+//! curl -H "Authorization: Bearer $ADMIN_KEY" http://localhost:1000/admin/jobs
+//! ```
+//!
+//! Run a job now.
+//! ```sh
+//! curl -X POST -H "Authorization: Bearer $ADMIN_KEY" http://localhost:1000/admin/jobs/vote_sync/run
+//! ```
+use std::{
+    str::FromStr,
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Instant,
+};
+
+use serde::Serialize;
+use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
+use tracing::{Instrument, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    error::AppError::{self, Conflict, InternalError, NotFound},
+    monitor::{record_bank, record_bank_refresh, record_cron},
+    snapshot::take_snapshot,
+    state::State,
+    telemetry::cron_span,
+    utils::now_ms,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum JobKind {
+    BankRefresh,
+    VoteSync,
+    Cleanup,
+    Snapshot,
+}
+
+impl JobKind {
+    pub const ALL: [JobKind; 4] = [
+        JobKind::BankRefresh,
+        JobKind::VoteSync,
+        JobKind::Cleanup,
+        JobKind::Snapshot,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            JobKind::BankRefresh => "bank_refresh",
+            JobKind::VoteSync => "vote_sync",
+            JobKind::Cleanup => "cleanup",
+            JobKind::Snapshot => "snapshot",
+        }
+    }
+
+    fn schedule(self, config: &Config) -> &str {
+        match self {
+            JobKind::BankRefresh => &config.bank_refresh_schedule,
+            JobKind::VoteSync => &config.vote_sync_schedule,
+            JobKind::Cleanup => &config.cleanup_schedule,
+            JobKind::Snapshot => &config.snapshot_schedule,
+        }
+    }
+}
+
+impl FromStr for JobKind {
+    type Err = AppError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        JobKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| NotFound(format!("job {name}")))
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Succeeded,
+    Failed,
+}
+
+#[derive(Serialize, Clone)]
+pub struct LastRun {
+    pub started_at_ms: u64,
+    pub duration_ms: u64,
+    pub outcome: Outcome,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct JobStatus {
+    pub name: &'static str,
+    pub schedule: String,
+    pub running: bool,
+    pub runs: u64,
+    pub failures: u64,
+    pub last_run: Option<LastRun>,
+    pub next_run_ms: Option<i64>,
+}
+
+#[derive(Default)]
+struct History {
+    runs: u64,
+    failures: u64,
+    last_run: Option<LastRun>,
+}
+
+struct Entry {
+    kind: JobKind,
+    schedule: String,
+    id: OnceLock<Uuid>,
+    running: AtomicBool,
+    history: Mutex<History>,
+}
+
+// clears the running flag when the run ends, panics included
+struct Running<'a>(&'a AtomicBool);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+pub struct JobRegistry {
+    entries: Vec<Entry>,
+    scheduler: OnceLock<JobScheduler>,
+}
+
+impl JobRegistry {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            entries: JobKind::ALL
+                .into_iter()
+                .map(|kind| Entry {
+                    kind,
+                    schedule: kind.schedule(config).to_string(),
+                    id: OnceLock::new(),
+                    running: AtomicBool::new(false),
+                    history: Mutex::new(History::default()),
+                })
+                .collect(),
+            scheduler: OnceLock::new(),
+        }
+    }
+
+    fn entry(&self, kind: JobKind) -> &Entry {
+        self.entries
+            .iter()
+            .find(|entry| entry.kind == kind)
+            .expect("Every job kind has an entry")
+    }
+
+    pub async fn statuses(&self) -> Vec<JobStatus> {
+        let mut statuses = Vec::with_capacity(self.entries.len());
+
+        for kind in JobKind::ALL {
+            statuses.push(self.status(kind).await);
+        }
+
+        statuses
+    }
+
+    pub async fn status(&self, kind: JobKind) -> JobStatus {
+        let entry = self.entry(kind);
+
+        let next_run_ms = match (self.scheduler.get(), entry.id.get()) {
+            (Some(scheduler), Some(id)) => scheduler
+                .clone()
+                .next_tick_for_job(*id)
+                .await
+                .ok()
+                .flatten()
+                .map(|tick| tick.timestamp_millis()),
+            _ => None,
+        };
+
+        let history = entry.history.lock().unwrap();
+
+        JobStatus {
+            name: kind.name(),
+            schedule: entry.schedule.clone(),
+            running: entry.running.load(Ordering::Acquire),
+            runs: history.runs,
+            failures: history.failures,
+            last_run: history.last_run.clone(),
+            next_run_ms,
+        }
+    }
+}
+
+pub async fn start_jobs(state: Arc<State>) -> Result<(), JobSchedulerError> {
+    let scheduler = JobScheduler::new().await?;
+
+    for kind in JobKind::ALL {
+        let entry = state.jobs.entry(kind);
+        let job_state = state.clone();
+
+        let job = Job::new_async(entry.schedule.as_str(), move |_uuid, _lock| {
+            let state = job_state.clone();
+
+            Box::pin(async move {
+                // overlapping runs are already logged
+                let _ = run_job(&state, kind).await;
+            })
+        })?;
+
+        let _ = entry.id.set(scheduler.add(job).await?);
+    }
+
+    scheduler.start().await?;
+    let _ = state.jobs.scheduler.set(scheduler);
+
+    Ok(())
+}
+
+pub async fn run_job(state: &Arc<State>, kind: JobKind) -> Result<JobStatus, AppError> {
+    let entry = state.jobs.entry(kind);
+
+    if entry.running.swap(true, Ordering::AcqRel) {
+        warn!("Skipping {}, previous run still going", kind.name());
+        return Err(Conflict(format!("{} is already running", kind.name())));
+    }
+    let running = Running(&entry.running);
+
+    let started_at_ms = now_ms();
+    let started = Instant::now();
+
+    let result = execute(state, kind)
+        .instrument(cron_span(kind.name()))
+        .await;
+    record_cron(kind.name(), started, result.is_ok());
+
+    let (outcome, message) = match result {
+        Ok(message) => {
+            info!("{}: {message}", kind.name());
+            (Outcome::Succeeded, message)
+        }
+        Err(e) => {
+            warn!("{} failed: {e}", kind.name());
+            (Outcome::Failed, e.to_string())
+        }
+    };
+
+    {
+        let mut history = entry.history.lock().unwrap();
+
+        history.runs += 1;
+        if let Outcome::Failed = outcome {
+            history.failures += 1;
+        }
+        history.last_run = Some(LastRun {
+            started_at_ms,
+            duration_ms: started.elapsed().as_millis() as u64,
+            outcome,
+            message,
+        });
+    }
+    drop(running);
+
+    Ok(state.jobs.status(kind).await)
+}
+
+async fn execute(state: &Arc<State>, kind: JobKind) -> Result<String, AppError> {
+    match kind {
+        JobKind::BankRefresh => {
+            let result = state.bank_source.fetch().await;
+            record_bank_refresh(result.is_ok());
+
+            let bank = result?;
+            record_bank(&bank);
+
+            let foods = bank.bank.foods.len();
+            let bank = Arc::new(bank);
+
+            // new foods get their counts before votes can reach them through the new bank,
+            // until the store connects it populates from whichever bank is loaded then
+            let Ok(store) = state.store() else {
+                state.remote_bank.store(bank);
+                return Ok(format!(
+                    "Loaded {foods} foods, votes and search not updated"
+                ));
+            };
+            let food_votes = store.populate(&bank.food_id_to_name).await?;
+            state.remote_bank.store(bank.clone());
+
+            // menus and served_today follow the new bank, skipped until search is up
+            let Ok(search) = state.search() else {
+                return Ok(format!("Loaded {foods} foods, search not updated"));
+            };
+            search.upsert_foods(&bank.bank, &food_votes).await?;
+
+            Ok(format!(
+                "Loaded {foods} foods, served on {}",
+                bank.bank.served_on
+            ))
+        }
+        JobKind::VoteSync => {
+            let store = state.store()?;
+            let search = state.search()?;
+            let remote_bank = state.remote_bank.load_full();
+
+            let food_votes = store.food_votes().await?;
+            search.upsert_foods(&remote_bank.bank, &food_votes).await?;
+
+            Ok(format!("Synced votes for {} foods", food_votes.len()))
+        }
+        JobKind::Cleanup => {
+            let store = state.store()?;
+            let retention_hours = state.config.hourly_retention_days * 24;
+
+            let hours = store.compact_votes(retention_hours).await?;
+
+            Ok(format!("Compacted {hours} hourly vote buckets"))
+        }
+        JobKind::Snapshot => {
+            let store = state.store()?;
+            let remote_bank = state.remote_bank.load_full();
+
+            let (path, snapshot) = take_snapshot(store.as_ref(), &remote_bank, &state.config)
+                .await
+                .map_err(|e| InternalError(Box::new(e)))?;
+
+            Ok(format!(
+                "Saved {} foods and {} users to {}",
+                snapshot.foods.len(),
+                snapshot.users.len(),
+                path.display()
+            ))
+        }
+    }
+}
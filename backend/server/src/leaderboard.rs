@@ -0,0 +1,79 @@
+//! # Leaderboard
+//!
+//! Top foods by votes.
+//!
+//! ## Query
+//! - `location`: only foods served at this location today, matching the bank's food location
+//! - `limit`: number of foods, default 10, capped at 100
+//! - `window`: hours, 0 (default) is all-time votes from the `foods` hash, otherwise trending by the
+//!   vote delta summed over the hourly buckets, capped at a week
+//!
+//! ## Response
+//! - Protobuf `Leaderboard`, entries of food id and votes ordered highest first
+//! - Names are not sent, the frontend already has the bank
+//! - `next_food_id` of the server's bank, so clients notice a stale bank
+use std::{cmp::Reverse, sync::Arc};
+
+use bank::payloads::{Leaderboard, LeaderboardEntry};
+use serde::Deserialize;
+
+use crate::{database::MAX_WINDOW_HOURS, error::AppError, state::State};
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    pub location: Option<String>,
+    pub limit: Option<usize>,
+    pub window: Option<u64>,
+}
+
+pub async fn get_leaderboard(
+    state: Arc<State>,
+    query: LeaderboardQuery,
+) -> Result<Leaderboard, AppError> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    // older hours are compacted into days and can no longer be summed by the hour
+    let window = query
+        .window
+        .unwrap_or(0)
+        .min(MAX_WINDOW_HOURS)
+        .min(state.config.hourly_retention_days * 24);
+
+    let store = state.store()?;
+    let votes = if window == 0 {
+        store.food_votes().await?
+    } else {
+        store.vote_deltas(window).await?
+    };
+
+    let remote_bank = state.remote_bank.load();
+
+    let mut entries: Vec<LeaderboardEntry> = votes
+        .into_iter()
+        .filter(|(id, _)| {
+            let Some(name) = remote_bank.food_id_to_name.get(*id as usize) else {
+                return false;
+            };
+
+            match &query.location {
+                Some(location) => remote_bank
+                    .bank
+                    .foods
+                    .get(name)
+                    .is_some_and(|food| &food.location == location),
+                None => !name.is_empty(),
+            }
+        })
+        .map(|(id, votes)| LeaderboardEntry { id, votes })
+        .collect();
+
+    entries.sort_unstable_by_key(|entry| (Reverse(entry.votes), entry.id));
+    entries.truncate(limit);
+
+    Ok(Leaderboard {
+        entries,
+        next_food_id: remote_bank.bank.next_food_id,
+    })
+}
@@ -27,6 +27,8 @@
 //! - If so, reject the request as it is certainly not a real human interaction
 //! - If not, allow the request through
 //! - Finally, update the HMAC timestamp, preferably at the end of the request life time
+//! - The server repeats the cookie check itself unless disabled, see [`token`]
+//! - Votes are additionally token bucket limited per IP inside the server, see [`limiter`]
 //!
 //!
 //!
@@ -63,6 +65,16 @@
 //! cargo doc
 //! `````
 //!
+//! Run the tests, the server ones use the memory vote store and stubs, no Docker needed.
+//! ```sh
+//! cargo test --workspace
+//! `````
+//!
+//! Also check the vote store rules against a throwaway Redis, its database is flushed.
+//! ```sh
+//! TEST_REDIS_URL=redis://localhost:6379/15 cargo test -p server --test store
+//! `````
+//!
 //!
 //!
 //! # Just
@@ -134,56 +146,89 @@
 //! ```sh
 //! just erase
 //! ```
-use std::{sync::Arc, time::Duration};
+use std::{net::SocketAddr, path::Path, process::exit, sync::Arc, time::Duration};
 
 use axum::{
     Router,
-    http::{Method, header::CONTENT_TYPE},
-    routing::{get, post},
+    http::{
+        HeaderName, Method,
+        header::{ACCEPT, CONTENT_TYPE},
+    },
+    middleware::{from_fn, from_fn_with_state},
+    routing::{MethodRouter, get, post},
 };
-use bank::get_remote_bank;
 use signal::{
     ctrl_c,
     unix::{SignalKind, signal},
 };
 use tokio::{net::TcpListener, signal};
-use tokio_cron_scheduler::{Job, JobScheduler};
 use tower_http::cors::CorsLayer;
-use tracing::info;
-use tracing_subscriber::{EnvFilter, fmt};
+use tracing::{error, info};
 
+pub mod admin;
+pub mod audit;
 pub mod config;
 pub mod database;
 pub mod error;
+pub mod fallback;
+pub mod health;
+pub mod jobs;
+pub mod leaderboard;
+pub mod limiter;
+pub mod manifest;
+pub mod memory;
+pub mod monitor;
+pub mod negotiate;
 pub mod routes;
 pub mod search;
+pub mod snapshot;
+pub mod source;
 pub mod state;
+pub mod store;
+pub mod telemetry;
+pub mod token;
 pub mod user;
 pub mod utils;
 
-use routes::{search_handler, votes_handler};
+use admin::{
+    audit_handler, bank_refresh_handler, change_votes_handler, food_handler, history_handler,
+    jobs_handler, rebuild_index_handler, run_job_handler, user_handler, verify_admin,
+};
+use config::Config;
+use error::BANK_VERSION_HEADER;
+use health::{healthz_handler, readyz_handler};
+use jobs::start_jobs;
+use limiter::rate_limit;
+use manifest::{Auth, ROUTES, Route};
+use monitor::{init_metrics, metrics_handler, record_bank, track_requests};
+use negotiate::negotiate_errors;
+use routes::{bank_handler, leaderboard_handler, search_handler, votes_handler};
 use state::State;
+use telemetry::{init_tracing, trace_requests};
+use token::verify_token;
 
 pub async fn start_server() {
-    fmt().with_env_filter(EnvFilter::from_default_env()).init();
+    let mut telemetry = init_tracing();
+    init_metrics();
+
+    info!("Loading config...");
+    let config = Config::load().unwrap_or_else(|errors| {
+        error!("{errors}");
+        exit(1);
+    });
 
     info!("Initializing state...");
-    let state = State::new().await;
+    let state = State::new(config).await;
+    telemetry.enable_otlp(state.config.otlp_endpoint.as_deref());
+    record_bank(&state.remote_bank.load());
 
     info!("Creating cron jobs...");
-    create_cron_jobs(state.clone()).await;
+    start_jobs(state.clone())
+        .await
+        .expect("Failed to start cron jobs");
 
     info!("Starting server...");
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::OPTIONS])
-        .allow_headers([CONTENT_TYPE])
-        .max_age(Duration::from_secs(60 * 60));
-
-    let app = Router::new()
-        .route("/votes", post(votes_handler))
-        .route("/search", get(search_handler))
-        .layer(cors)
-        .with_state(state.clone());
+    let app = router(state.clone());
 
     let address = format!("0.0.0.0:{}", state.config.port);
     info!("Binding to {address}");
@@ -191,14 +236,105 @@ pub async fn start_server() {
     let listener = TcpListener::bind(&address).await.unwrap();
     info!("Server running on {address}");
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await
+    .unwrap();
 
+    telemetry.shutdown();
     println!("Server shutting down...");
 }
 
+// every route with its layers, tests drive it without binding a socket
+pub fn router(state: Arc<State>) -> Router {
+    let cors = CorsLayer::new()
+        .allow_methods([Method::GET, Method::OPTIONS])
+        .allow_headers([CONTENT_TYPE, ACCEPT])
+        .expose_headers([HeaderName::from_static(BANK_VERSION_HEADER)])
+        .max_age(Duration::from_secs(60 * 60));
+
+    // every path comes from the manifest, so nothing is served without an entry in it
+    let routes = |auth: Auth| ROUTES.iter().filter(move |route| route.auth == auth);
+
+    let mut app = Router::new();
+    for route in routes(Auth::Token) {
+        app = app.route(route.path, handler(route, &state));
+    }
+    app = app.layer(from_fn_with_state(state.clone(), verify_token));
+
+    for route in routes(Auth::None) {
+        app = app.route(route.path, handler(route, &state));
+    }
+    for route in routes(Auth::Admin) {
+        let handler =
+            handler(route, &state).route_layer(from_fn_with_state(state.clone(), verify_admin));
+        app = app.route(route.path, handler);
+    }
+
+    app.layer(from_fn(negotiate_errors))
+        .layer(cors)
+        .layer(from_fn(track_requests))
+        .layer(from_fn(trace_requests))
+        .with_state(state)
+}
+
+// the handler of a manifest route, panics for an entry nothing serves
+fn handler(route: &Route, state: &Arc<State>) -> MethodRouter<Arc<State>> {
+    match (route.method, route.path) {
+        ("POST", "/votes") => {
+            post(votes_handler).route_layer(from_fn_with_state(state.clone(), rate_limit))
+        }
+        ("GET", "/search") => get(search_handler),
+        ("GET", "/leaderboard") => get(leaderboard_handler),
+        ("GET", "/bank") => get(bank_handler),
+        ("GET", "/metrics") => get(metrics_handler),
+        ("GET", "/healthz") => get(healthz_handler),
+        ("GET", "/readyz") => get(readyz_handler),
+        ("GET", "/admin/jobs") => get(jobs_handler),
+        ("POST", "/admin/jobs/{name}/run") => post(run_job_handler),
+        ("GET", "/admin/foods/{key}") => get(food_handler),
+        ("POST", "/admin/foods/{key}/votes") => post(change_votes_handler),
+        ("GET", "/admin/foods/{key}/history") => get(history_handler),
+        ("GET", "/admin/users/{user}") => get(user_handler),
+        ("POST", "/admin/bank/refresh") => post(bank_refresh_handler),
+        ("POST", "/admin/index/rebuild") => post(rebuild_index_handler),
+        ("GET", "/admin/audit") => get(audit_handler),
+        (method, path) => panic!("no handler for manifest route {method} {path}"),
+    }
+}
+
+pub fn print_config() {
+    match Config::load() {
+        Ok(config) => print!("{}", config.to_toml()),
+        Err(errors) => {
+            eprint!("{errors}");
+            exit(1);
+        }
+    }
+}
+
+pub async fn restore_snapshot(path: &str) {
+    let config = Config::load().unwrap_or_else(|errors| {
+        eprint!("{errors}");
+        exit(1);
+    });
+
+    match snapshot::restore_snapshot(&config, Path::new(path)).await {
+        Ok(snapshot) => println!(
+            "Restored {} foods and {} users from {path}",
+            snapshot.foods.len(),
+            snapshot.users.len()
+        ),
+        Err(e) => {
+            eprintln!("Failed to restore {path}: {e}");
+            exit(1);
+        }
+    }
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         ctrl_c().await.expect("Failed to install Ctrl+C handler");
@@ -224,38 +360,3 @@ async fn shutdown_signal() {
         _ = terminate => {},
     }
 }
-
-fn remote_bank_refresh_job(state: Arc<State>) -> Job {
-    Job::new_async("0 0 4 * * *", move |_uuid, _lock| {
-        let state = state.clone();
-
-        Box::pin(async move {
-            let new_bank = match get_remote_bank().await {
-                Ok(bank) => {
-                    info!("Successfully refreshed remote bank");
-                    bank
-                }
-                Err(e) => {
-                    info!("Failed to fetch remote bank: {}", e);
-                    return;
-                }
-            };
-
-            state.remote_bank.store(Arc::new(new_bank));
-        })
-    })
-    .unwrap()
-}
-
-async fn create_cron_jobs(state: Arc<State>) {
-    let scheduler = JobScheduler::new().await.unwrap();
-
-    scheduler
-        .add(remote_bank_refresh_job(state.clone()))
-        .await
-        .unwrap();
-
-    tokio::spawn(async move {
-        scheduler.start().await.expect("Failed to start scheduler");
-    });
-}
@@ -0,0 +1,165 @@
+//! # Rate Limiting
+//!
+//! Token buckets guarding the votes endpoint, stored in Redis so every replica shares them.
+//!
+//! ## Buckets
+//! - Per IP: `limit:ip:<ip>`, larger burst as many students share campus NAT
+//! - No per user bucket, the `valid_id` cookie is not signed and a client could rotate it for a fresh one
+//! - Buckets expire once they would have fully refilled
+//!
+//! ## Rejection
+//! - 429 with `Retry-After` in seconds until the bucket refills a token, rounded up
+//! - Bit flips per request are capped separately by `MAX_VOTE_FLIPS`, also 429 with the IP bucket's `Retry-After`
+//!
+//! ## Client IP
+//! - The socket peer, unless it is one of `TRUSTED_PROXIES` (IPs or CIDR ranges, comma separated)
+//! - Behind a trusted proxy, `X-Forwarded-For` is walked from the right past every trusted hop,
+//!   the first untrusted one is the client, so entries a client prepends are never used
+//! - Without trusted proxies the header is ignored entirely
+use std::{
+    fmt::{self, Display},
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Serialize, Serializer};
+
+use crate::{
+    database::{Bucket, RATE_LIMIT_PREFIX},
+    error::AppError::{self, RateLimited},
+    state::State as AppState,
+};
+
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+// addresses and prefix lengths of the proxies allowed to set X-Forwarded-For
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct TrustedProxies(Vec<(IpAddr, u8)>);
+
+impl TrustedProxies {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|&(network, prefix)| match (network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        })
+    }
+}
+
+impl FromStr for TrustedProxies {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (ip, prefix) = entry.split_once('/').unwrap_or((entry, ""));
+                let ip: IpAddr = ip
+                    .parse()
+                    .map_err(|_| format!("{entry:?} is not an IP or CIDR range"))?;
+                let max = if ip.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix {
+                    "" => max,
+                    prefix => prefix
+                        .parse()
+                        .ok()
+                        .filter(|prefix| *prefix <= max)
+                        .ok_or_else(|| format!("{entry:?} has an invalid prefix length"))?,
+                };
+
+                Ok((ip, prefix))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl Display for TrustedProxies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = self
+            .0
+            .iter()
+            .map(|(ip, prefix)| format!("{ip}/{prefix}"))
+            .collect();
+
+        write!(f, "{}", entries.join(", "))
+    }
+}
+
+impl Serialize for TrustedProxies {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+pub async fn rate_limit(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Some(ip) = client_ip(&request, &state.config.trusted_proxies) else {
+        return Ok(next.run(request).await);
+    };
+
+    let bucket = Bucket {
+        key: format!("{RATE_LIMIT_PREFIX}:ip:{ip}"),
+        burst: state.config.ip_rate_burst,
+        refill_ms: state.config.ip_rate_refill_ms,
+    };
+
+    let retry_after_ms = state.store()?.take_tokens(&[bucket]).await?;
+    if retry_after_ms > 0 {
+        return Err(RateLimited(retry_after_ms.div_ceil(1000)));
+    }
+
+    Ok(next.run(request).await)
+}
+
+pub fn client_ip(request: &Request, proxies: &TrustedProxies) -> Option<IpAddr> {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip().to_canonical())?;
+
+    if !proxies.contains(peer) {
+        return Some(peer);
+    }
+
+    let hops: Vec<&str> = request
+        .headers()
+        .get_all(FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+
+    // a garbled hop ends the walk at the last address a trusted proxy vouched for
+    let mut client = peer;
+    for hop in hops.iter().rev() {
+        let Ok(ip) = hop.parse::<IpAddr>() else {
+            break;
+        };
+        client = ip.to_canonical();
+
+        if !proxies.contains(client) {
+            break;
+        }
+    }
+
+    Some(client)
+}
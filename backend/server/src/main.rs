@@ -1,4 +1,23 @@
+use std::env;
+
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.iter().any(|arg| arg == "--print-config") {
+        server::print_config();
+        return;
+    }
+
+    if let Some(position) = args.iter().position(|arg| arg == "--restore") {
+        let Some(path) = args.get(position + 1) else {
+            eprintln!("Usage: server --restore <snapshot file>");
+            std::process::exit(1);
+        };
+
+        server::restore_snapshot(path).await;
+        return;
+    }
+
     server::start_server().await;
 }
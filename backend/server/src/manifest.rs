@@ -0,0 +1,207 @@
+//! # Route Manifest
+//!
+//! Every endpoint of [`crate::router`], the contract `codegen` hands to the frontend next to the proto types.
+//!
+//! ## Notes
+//! - Messages are fully qualified proto names, `payloads.Votes` is `Votes` in `payloads.proto`
+//! - Public routes speak protobuf and JSON, see [`crate::negotiate`]
+//! - Errors are a `payloads.Error` when the client named a format
+//! - [`crate::router`] serves exactly these routes, a new one needs an entry here and a handler there
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Auth {
+    // token cookie, skipped with VERIFY_TOKEN=false
+    Token,
+    // Authorization: Bearer <ADMIN_KEY>
+    Admin,
+    None,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Body {
+    // proto message, either format
+    Message(&'static str),
+    // serde struct of the server, JSON only
+    Json(&'static str),
+    Text,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct Route {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub auth: Auth,
+    pub query: &'static [&'static str],
+    pub request: Option<Body>,
+    pub response: Body,
+    pub summary: &'static str,
+}
+
+const fn public(
+    method: &'static str,
+    path: &'static str,
+    request: Option<&'static str>,
+    response: &'static str,
+    summary: &'static str,
+) -> Route {
+    Route {
+        method,
+        path,
+        auth: Auth::Token,
+        query: &[],
+        request: match request {
+            Some(message) => Some(Body::Message(message)),
+            None => None,
+        },
+        response: Body::Message(response),
+        summary,
+    }
+}
+
+const fn admin(
+    method: &'static str,
+    path: &'static str,
+    request: Option<&'static str>,
+    response: &'static str,
+    summary: &'static str,
+) -> Route {
+    Route {
+        method,
+        path,
+        auth: Auth::Admin,
+        query: &[],
+        request: match request {
+            Some(body) => Some(Body::Json(body)),
+            None => None,
+        },
+        response: Body::Json(response),
+        summary,
+    }
+}
+
+pub const ROUTES: &[Route] = &[
+    public(
+        "POST",
+        "/votes",
+        Some("payloads.Votes"),
+        "payloads.VotesAccepted",
+        "Applies the flipped bits between two vote bitmaps",
+    ),
+    public(
+        "GET",
+        "/search",
+        Some("payloads.SearchRequest"),
+        "payloads.SearchResponse",
+        "Searches the food index, with filters, sort, paging, highlights and facet counts",
+    ),
+    Route {
+        query: &["location", "limit", "window"],
+        ..public(
+            "GET",
+            "/leaderboard",
+            None,
+            "payloads.Leaderboard",
+            "Most voted foods, optionally of one location or the last window hours",
+        )
+    },
+    public("GET", "/bank", None, "foods.Bank", "Current food bank"),
+    Route {
+        method: "GET",
+        path: "/metrics",
+        auth: Auth::None,
+        query: &[],
+        request: None,
+        response: Body::Text,
+        summary: "Prometheus metrics",
+    },
+    Route {
+        method: "GET",
+        path: "/healthz",
+        auth: Auth::None,
+        query: &[],
+        request: None,
+        response: Body::Text,
+        summary: "Liveness",
+    },
+    Route {
+        method: "GET",
+        path: "/readyz",
+        auth: Auth::None,
+        query: &[],
+        request: None,
+        response: Body::Json("Readiness"),
+        summary: "Readiness of Redis and Meilisearch",
+    },
+    admin(
+        "GET",
+        "/admin/jobs",
+        None,
+        "JobStatus[]",
+        "Status of every cron job",
+    ),
+    admin(
+        "POST",
+        "/admin/jobs/{name}/run",
+        None,
+        "JobStatus",
+        "Runs a job now",
+    ),
+    admin(
+        "GET",
+        "/admin/foods/{key}",
+        None,
+        "FoodReport",
+        "Bank entry, votes and search document of a food id or name",
+    ),
+    admin(
+        "POST",
+        "/admin/foods/{key}/votes",
+        Some("VoteChange"),
+        "VotesChanged",
+        "Sets or adjusts the votes of a food",
+    ),
+    Route {
+        query: &["bucket", "count"],
+        ..admin(
+            "GET",
+            "/admin/foods/{key}/history",
+            None,
+            "VoteHistory",
+            "Vote delta of a food per hour or day, oldest first",
+        )
+    },
+    admin(
+        "GET",
+        "/admin/users/{user}",
+        None,
+        "UserReport",
+        "Latest vote bitmap of a user",
+    ),
+    admin(
+        "POST",
+        "/admin/bank/refresh",
+        None,
+        "JobStatus",
+        "Runs the bank_refresh job now",
+    ),
+    admin(
+        "POST",
+        "/admin/index/rebuild",
+        None,
+        "number",
+        "Rebuilds the search index",
+    ),
+    Route {
+        query: &["limit"],
+        ..admin(
+            "GET",
+            "/admin/audit",
+            None,
+            "AuditEntry[]",
+            "Latest admin changes, newest first",
+        )
+    },
+];
@@ -0,0 +1,308 @@
+//! # Memory Store
+//!
+//! In-process [`VoteStore`], mirrors the Redis Lua scripts in [`crate::database`].
+//!
+//! ## Notes
+//! - One lock around everything, each call is atomic like a Lua script
+//! - Rate limit buckets that would have fully refilled are dropped once there are many of them
+//! - Nothing survives a restart without snapshots, see [`crate::snapshot`]
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    database::{AUDIT_LENGTH, Bucket, HOURS_PER_DAY, Vote, VoteChange, VoteState, current_hour},
+    error::AppError::{self, NotFound},
+    store::VoteStore,
+    utils::now_ms,
+};
+
+const PRUNE_BUCKETS_AFTER: usize = 10_000;
+
+struct TokenBucket {
+    tokens: f64,
+    updated_ms: u64,
+    expires_ms: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    foods: HashMap<u32, i64>,
+    hourly: BTreeMap<u64, HashMap<u32, i64>>,
+    daily: HashMap<u64, HashMap<u32, i64>>,
+    users: HashMap<String, Vec<u8>>,
+    buckets: HashMap<String, TokenBucket>,
+    audit: VecDeque<String>,
+}
+
+#[derive(Default)]
+pub struct MemoryStore {
+    inner: Mutex<Inner>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl VoteStore for MemoryStore {
+    async fn populate(&self, food_id_to_name: &[String]) -> Result<HashMap<u32, i64>, AppError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        Ok(food_id_to_name
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| !name.is_empty())
+            .map(|(id, _)| {
+                let id = id as u32;
+                (id, *inner.foods.entry(id).or_insert(0))
+            })
+            .collect())
+    }
+
+    async fn update_foods(&self, votes: &[(isize, Vote)]) -> Result<(), AppError> {
+        let mut inner = self.inner.lock().unwrap();
+        let hour = current_hour();
+
+        // an unknown food rejects the whole batch before anything is written
+        if let Some((food_key, _)) = votes
+            .iter()
+            .find(|(food_key, _)| !inner.foods.contains_key(&(*food_key as u32)))
+        {
+            return Err(NotFound(format!("votes for food {food_key}")));
+        }
+
+        for (food_key, vote) in votes {
+            let food_id = *food_key as u32;
+            let amount = vote.as_str() as i64;
+
+            let current = inner.foods.entry(food_id).or_default();
+            if *current + amount < 0 {
+                continue;
+            }
+            *current += amount;
+
+            *inner
+                .hourly
+                .entry(hour)
+                .or_default()
+                .entry(food_id)
+                .or_insert(0) += amount;
+        }
+
+        Ok(())
+    }
+
+    async fn food_votes(&self) -> Result<HashMap<u32, i64>, AppError> {
+        Ok(self.inner.lock().unwrap().foods.clone())
+    }
+
+    async fn votes(&self, food_id: u32) -> Result<Option<i64>, AppError> {
+        Ok(self.inner.lock().unwrap().foods.get(&food_id).copied())
+    }
+
+    async fn vote_deltas(&self, hours: u64) -> Result<HashMap<u32, i64>, AppError> {
+        let inner = self.inner.lock().unwrap();
+        let now = current_hour();
+
+        let mut deltas = HashMap::new();
+        for bucket in inner
+            .hourly
+            .range(now.saturating_sub(hours.saturating_sub(1))..=now)
+            .map(|(_, bucket)| bucket)
+        {
+            for (food_id, delta) in bucket {
+                *deltas.entry(*food_id).or_insert(0) += delta;
+            }
+        }
+
+        Ok(deltas)
+    }
+
+    async fn change_votes(
+        &self,
+        food_id: u32,
+        change: VoteChange,
+    ) -> Result<Option<(i64, i64)>, AppError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let Some(current) = inner.foods.get_mut(&food_id) else {
+            return Ok(None);
+        };
+
+        let before = *current;
+        *current = match change {
+            VoteChange::Set(votes) => votes,
+            VoteChange::Adjust(delta) => before + delta,
+        }
+        .max(0);
+
+        Ok(Some((before, *current)))
+    }
+
+    async fn hourly_history(&self, food_id: u32, hours: u64) -> Result<Vec<(u64, i64)>, AppError> {
+        let inner = self.inner.lock().unwrap();
+        let now = current_hour();
+
+        Ok((now.saturating_sub(hours.saturating_sub(1))..=now)
+            .map(|hour| (hour, delta(inner.hourly.get(&hour), food_id)))
+            .collect())
+    }
+
+    async fn daily_history(&self, food_id: u32, days: u64) -> Result<Vec<(u64, i64)>, AppError> {
+        let inner = self.inner.lock().unwrap();
+        let today = current_hour() / HOURS_PER_DAY;
+        let first_day = today.saturating_sub(days.saturating_sub(1));
+
+        let mut history: Vec<(u64, i64)> = (first_day..=today)
+            .map(|day| (day, delta(inner.daily.get(&day), food_id)))
+            .collect();
+
+        for (hour, bucket) in inner.hourly.range(first_day * HOURS_PER_DAY..) {
+            let index = (hour / HOURS_PER_DAY - first_day) as usize;
+            if let Some((_, total)) = history.get_mut(index) {
+                *total += delta(Some(bucket), food_id);
+            }
+        }
+
+        Ok(history)
+    }
+
+    async fn compact_votes(&self, retention_hours: u64) -> Result<usize, AppError> {
+        let mut inner = self.inner.lock().unwrap();
+        let cutoff = current_hour().saturating_sub(retention_hours);
+
+        let kept = inner.hourly.split_off(&cutoff);
+        let compacted = std::mem::replace(&mut inner.hourly, kept);
+
+        for (hour, bucket) in &compacted {
+            let day = inner.daily.entry(hour / HOURS_PER_DAY).or_default();
+
+            for (food_id, delta) in bucket {
+                *day.entry(*food_id).or_insert(0) += delta;
+            }
+        }
+
+        Ok(compacted.len())
+    }
+
+    async fn user_votes(&self, user_id: &str) -> Result<Option<Vec<u8>>, AppError> {
+        Ok(self.inner.lock().unwrap().users.get(user_id).cloned())
+    }
+
+    async fn set_user_votes(&self, user_id: &str, bit_map: &[u8]) -> Result<(), AppError> {
+        self.inner
+            .lock()
+            .unwrap()
+            .users
+            .insert(user_id.to_string(), bit_map.to_vec());
+
+        Ok(())
+    }
+
+    async fn vote_state(&self) -> Result<VoteState, AppError> {
+        let inner = self.inner.lock().unwrap();
+
+        Ok(VoteState {
+            foods: inner.foods.clone(),
+            users: inner.users.clone(),
+        })
+    }
+
+    async fn restore_vote_state(&self, vote_state: &VoteState) -> Result<(), AppError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.foods.extend(&vote_state.foods);
+        inner.users.extend(
+            vote_state
+                .users
+                .iter()
+                .map(|(user, bit_map)| (user.clone(), bit_map.clone())),
+        );
+
+        Ok(())
+    }
+
+    async fn take_tokens(&self, buckets: &[Bucket]) -> Result<u64, AppError> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = now_ms();
+
+        if inner.buckets.len() > PRUNE_BUCKETS_AFTER {
+            inner.buckets.retain(|_, bucket| bucket.expires_ms > now);
+        }
+
+        let mut retry_after = 0;
+        let mut tokens = Vec::with_capacity(buckets.len());
+
+        for bucket in buckets {
+            let burst = bucket.burst as f64;
+            let refill = bucket.refill_ms as f64;
+
+            let (stored, updated_ms) = match inner.buckets.get(&bucket.key) {
+                Some(stored) if stored.expires_ms > now => (stored.tokens, stored.updated_ms),
+                _ => (burst, now),
+            };
+            let available = burst.min(stored + now.saturating_sub(updated_ms) as f64 / refill);
+
+            if available < 1.0 {
+                retry_after = retry_after.max(((1.0 - available) * refill).ceil() as u64);
+            }
+
+            tokens.push(available);
+        }
+
+        if retry_after > 0 {
+            return Ok(retry_after);
+        }
+
+        for (bucket, available) in buckets.iter().zip(tokens) {
+            inner.buckets.insert(
+                bucket.key.clone(),
+                TokenBucket {
+                    tokens: available - 1.0,
+                    updated_ms: now,
+                    expires_ms: now + bucket.burst as u64 * bucket.refill_ms,
+                },
+            );
+        }
+
+        Ok(0)
+    }
+
+    async fn push_audit(&self, entry: &str) -> Result<(), AppError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.audit.push_front(entry.to_string());
+        inner.audit.truncate(AUDIT_LENGTH);
+
+        Ok(())
+    }
+
+    async fn audit(&self, count: usize) -> Result<Vec<String>, AppError> {
+        Ok(self
+            .inner
+            .lock()
+            .unwrap()
+            .audit
+            .iter()
+            .take(count)
+            .cloned()
+            .collect())
+    }
+
+    async fn ping(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+}
+
+fn delta(bucket: Option<&HashMap<u32, i64>>, food_id: u32) -> i64 {
+    bucket
+        .and_then(|bucket| bucket.get(&food_id))
+        .copied()
+        .unwrap_or(0)
+}
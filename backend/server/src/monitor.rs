@@ -0,0 +1,135 @@
+//! # Monitoring
+//!
+//! Prometheus metrics served at `/metrics`, scraped by the monitor stack and graphed in Grafana.
+//!
+//! ## Metrics
+//! - `http_requests_total` / `http_request_duration_seconds`: per route, method and status
+//! - `votes_applied_total`: increments and decrements sent to Redis
+//! - `redis_script_duration_seconds` / `redis_script_errors_total`: per Lua script
+//! - `meili_task_duration_seconds`: per index and task outcome
+//! - `search_fallback_total`: searches served from memory, per reason
+//! - `bank_refresh_total`: remote bank refreshes per outcome
+//! - `bank_next_food_id` / `bank_foods`: version and size of the loaded bank
+//! - `cron_job_duration_seconds` / `cron_job_last_run_timestamp_seconds`: per cron job
+//! - `cron_job_runs_total`: per cron job and outcome
+//!
+//! ## Dashboard
+//! - `monitor/grafana/dashboards/server.json`
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bank::RemoteBank;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use once_cell::sync::Lazy;
+
+pub const HTTP_REQUESTS: &str = "http_requests_total";
+pub const HTTP_DURATION: &str = "http_request_duration_seconds";
+pub const VOTES_APPLIED: &str = "votes_applied_total";
+pub const REDIS_DURATION: &str = "redis_script_duration_seconds";
+pub const REDIS_ERRORS: &str = "redis_script_errors_total";
+pub const MEILI_DURATION: &str = "meili_task_duration_seconds";
+pub const SEARCH_FALLBACKS: &str = "search_fallback_total";
+pub const BANK_REFRESHES: &str = "bank_refresh_total";
+pub const BANK_NEXT_FOOD_ID: &str = "bank_next_food_id";
+pub const BANK_FOODS: &str = "bank_foods";
+pub const CRON_DURATION: &str = "cron_job_duration_seconds";
+pub const CRON_LAST_RUN: &str = "cron_job_last_run_timestamp_seconds";
+pub const CRON_RUNS: &str = "cron_job_runs_total";
+
+const BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+static METRICS: Lazy<PrometheusHandle> = Lazy::new(|| {
+    PrometheusBuilder::new()
+        .set_buckets(BUCKETS)
+        .unwrap()
+        .install_recorder()
+        .expect("Failed to install metrics recorder")
+});
+
+pub fn init_metrics() {
+    let handle = Lazy::force(&METRICS).clone();
+
+    // drains histograms, normally done by the exporter's own listener which we do not use
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(UPKEEP_INTERVAL).await;
+            handle.run_upkeep();
+        }
+    });
+}
+
+pub async fn metrics_handler() -> impl IntoResponse {
+    METRICS.render()
+}
+
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+    let status = response.status().as_u16().to_string();
+
+    counter!(HTTP_REQUESTS, "route" => route.clone(), "method" => method.clone(), "status" => status)
+        .increment(1);
+    histogram!(HTTP_DURATION, "route" => route, "method" => method)
+        .record(started.elapsed().as_secs_f64());
+
+    response
+}
+
+pub fn record_redis<T, E>(script: &'static str, started: Instant, result: &Result<T, E>) {
+    histogram!(REDIS_DURATION, "script" => script).record(started.elapsed().as_secs_f64());
+
+    if result.is_err() {
+        counter!(REDIS_ERRORS, "script" => script).increment(1);
+    }
+}
+
+pub fn record_meili(index: &str, started: Instant, succeeded: bool) {
+    let outcome = if succeeded { "succeeded" } else { "failed" };
+
+    histogram!(MEILI_DURATION, "index" => index.to_string(), "outcome" => outcome)
+        .record(started.elapsed().as_secs_f64());
+}
+
+// not_ready before the first index finished, failed when Meilisearch errored
+pub fn record_search_fallback(reason: &'static str) {
+    counter!(SEARCH_FALLBACKS, "reason" => reason).increment(1);
+}
+
+pub fn record_bank(remote_bank: &RemoteBank) {
+    gauge!(BANK_NEXT_FOOD_ID).set(remote_bank.bank.next_food_id as f64);
+    gauge!(BANK_FOODS).set(remote_bank.bank.foods.len() as f64);
+}
+
+pub fn record_bank_refresh(succeeded: bool) {
+    let outcome = if succeeded { "succeeded" } else { "failed" };
+
+    counter!(BANK_REFRESHES, "outcome" => outcome).increment(1);
+}
+
+pub fn record_cron(job: &'static str, started: Instant, succeeded: bool) {
+    let outcome = if succeeded { "succeeded" } else { "failed" };
+
+    counter!(CRON_RUNS, "job" => job, "outcome" => outcome).increment(1);
+    histogram!(CRON_DURATION, "job" => job).record(started.elapsed().as_secs_f64());
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64())
+        .unwrap_or_default();
+    gauge!(CRON_LAST_RUN, "job" => job).set(now);
+}
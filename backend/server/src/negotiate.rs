@@ -0,0 +1,176 @@
+//! # Content Negotiation
+//!
+//! Public routes speak protobuf and JSON, the same `payloads` and `foods` messages either way.
+//!
+//! ## Requests
+//! - Body format from `Content-Type`, protobuf when missing so existing clients keep working
+//! - JSON uses the protobuf field names, missing fields decode as their defaults, `bytes` are arrays of numbers
+//!
+//! ## Responses
+//! - `Accept` picks the format, then the request's `Content-Type`, protobuf otherwise
+//! - Errors are a `payloads.Error` with a stable `code`, plain text when the client named neither format
+//! - Admin and health endpoints answer JSON only, `/metrics` the Prometheus text format
+//!
+//! ## Commands
+//!
+//! Leaderboard as JSON, with `VERIFY_TOKEN=false`.
+//! ```sh
+//! curl -H "Accept: application/json" "http://localhost:1000/leaderboard?limit=3"
+//! ```
+//!
+//! Vote for food 0.
+//! ```sh
+//! curl -X POST -H "Content-Type: application/json" \
+//!     -d '{"old_bit_map": [0], "new_bit_map": [1], "next_food_id": 3}' http://localhost:1000/votes
+//! ```
+use std::convert::Infallible;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
+        header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE},
+        request::Parts,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bank::payloads::Error as ErrorMessage;
+use prost::Message;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::error::AppError::{self, MalformedPayload};
+
+pub const PROTOBUF: &str = "application/x-protobuf";
+pub const JSON: &str = "application/json";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Protobuf,
+    Json,
+}
+
+impl Format {
+    // first supported media type of a Content-Type or Accept value, q values are ignored
+    fn parse(value: &str) -> Option<Self> {
+        value
+            .split(',')
+            .filter_map(|media| media.split(';').next())
+            .map(str::trim)
+            .find_map(|media| match media {
+                PROTOBUF | "application/protobuf" => Some(Format::Protobuf),
+                JSON => Some(Format::Json),
+                _ => None,
+            })
+    }
+
+    fn from_header(headers: &HeaderMap, name: HeaderName) -> Option<Self> {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(Self::parse)
+    }
+
+    pub fn request(headers: &HeaderMap) -> Self {
+        Self::from_header(headers, CONTENT_TYPE).unwrap_or(Format::Protobuf)
+    }
+
+    // None when the client named neither format
+    pub fn response(headers: &HeaderMap) -> Option<Self> {
+        Self::from_header(headers, ACCEPT).or_else(|| Self::from_header(headers, CONTENT_TYPE))
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Protobuf => PROTOBUF,
+            Format::Json => JSON,
+        }
+    }
+
+    pub fn encode<M: Message + Serialize>(self, message: &M) -> Vec<u8> {
+        match self {
+            Format::Protobuf => message.encode_to_vec(),
+            // derived Serialize on plain structs and maps with string keys does not fail
+            Format::Json => serde_json::to_vec(message).unwrap_or_default(),
+        }
+    }
+
+    pub fn decode<M: Message + Default + DeserializeOwned>(
+        self,
+        bytes: &[u8],
+    ) -> Result<M, AppError> {
+        match self {
+            Format::Protobuf => M::decode(bytes).map_err(|_| MalformedPayload),
+            Format::Json => serde_json::from_slice(bytes).map_err(|_| MalformedPayload),
+        }
+    }
+}
+
+// request body decoded by its Content-Type
+pub struct Payload<M>(pub M);
+
+impl<S, M> FromRequest<S> for Payload<M>
+where
+    S: Send + Sync,
+    M: Message + Default + DeserializeOwned,
+{
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let format = Format::request(request.headers());
+        let bytes = Bytes::from_request(request, state)
+            .await
+            .map_err(|_| MalformedPayload)?;
+
+        format.decode(&bytes).map(Payload)
+    }
+}
+
+// response format of the request, protobuf when the client named neither
+pub struct Accepts(pub Format);
+
+impl<S: Send + Sync> FromRequestParts<S> for Accepts {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Accepts(
+            Format::response(&parts.headers).unwrap_or(Format::Protobuf),
+        ))
+    }
+}
+
+impl Accepts {
+    pub fn respond<M: Message + Serialize>(&self, message: &M) -> Response {
+        (
+            StatusCode::OK,
+            [(CONTENT_TYPE, self.0.content_type())],
+            self.0.encode(message),
+        )
+            .into_response()
+    }
+}
+
+// re-encodes error responses, AppError leaves its code and message in the extensions
+pub async fn negotiate_errors(request: Request, next: Next) -> Response {
+    let format = Format::response(request.headers());
+    let mut response = next.run(request).await;
+
+    let Some(format) = format else {
+        return response;
+    };
+    let Some(error) = response.extensions_mut().remove::<ErrorMessage>() else {
+        return response;
+    };
+
+    let headers = response.headers_mut();
+    headers.remove(CONTENT_LENGTH);
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(format.content_type()),
+    );
+    *response.body_mut() = Body::from(format.encode(&error));
+
+    response
+}
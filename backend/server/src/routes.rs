@@ -1,32 +1,88 @@
 use std::sync::Arc;
 
-use axum::{Json, body::Bytes, extract::State, http::StatusCode, response::IntoResponse};
-use serde::Deserialize;
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::IntoResponse,
+};
+use bank::payloads::{SearchRequest, SearchResponse, Votes, VotesAccepted};
+use metrics::counter;
 #[cfg(feature = "verbose")]
 use tracing::info;
 
 use crate::{
-    database::update_foods, error::AppError, state::State as AppState, utils::get_votes_from_body,
+    database::Vote,
+    error::AppError,
+    leaderboard::{LeaderboardQuery, get_leaderboard},
+    monitor::VOTES_APPLIED,
+    negotiate::{Accepts, Payload},
+    search::{FoodQuery, SearchBounds},
+    state::State as AppState,
+    utils::{USER_COOKIE, get_cookie, get_votes_from_body},
 };
 
-#[derive(Deserialize)]
-pub struct Token {
-    token: String,
-}
-
 pub async fn votes_handler(
     State(state): State<Arc<AppState>>,
-    body: Bytes,
+    headers: HeaderMap,
+    accepts: Accepts,
+    Payload(vote_maps): Payload<Votes>,
 ) -> Result<impl IntoResponse, AppError> {
-    let votes = get_votes_from_body(state.clone(), body)?;
+    let (votes, bit_map) = get_votes_from_body(state.clone(), vote_maps)?;
 
     #[cfg(feature = "verbose")]
     info!("Length of votes: {}", votes.len());
-    update_foods(&mut state.redis_connection.clone(), &votes).await?;
+    let store = state.store()?;
+    store.update_foods(&votes).await?;
+
+    if let Some(user_id) = get_cookie(&headers, USER_COOKIE) {
+        store.set_user_votes(user_id, &bit_map).await?;
+    }
+
+    let increments = votes
+        .iter()
+        .filter(|(_, vote)| matches!(vote, Vote::Increment))
+        .count();
+    counter!(VOTES_APPLIED, "vote" => "increment").increment(increments as u64);
+    counter!(VOTES_APPLIED, "vote" => "decrement").increment((votes.len() - increments) as u64);
+
+    Ok(accepts.respond(&VotesAccepted {
+        votes: votes.len() as u32,
+    }))
+}
+
+pub async fn search_handler(
+    State(state): State<Arc<AppState>>,
+    accepts: Accepts,
+    Payload(request): Payload<SearchRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let token = request.token.clone();
+    let query = FoodQuery::parse(request, SearchBounds::from(&state.config))?;
+    let results = state.search_foods(&query).await?;
+
+    Ok(accepts.respond(&SearchResponse {
+        token,
+        ids: results.ids,
+        total: results.total as u32,
+        locations: results.facets.locations,
+        meals: results.facets.meals,
+        tags: results.facets.tags,
+        highlights: results.highlights,
+    }))
+}
 
-    Ok((StatusCode::OK, "Accepted").into_response())
+pub async fn bank_handler(
+    State(state): State<Arc<AppState>>,
+    accepts: Accepts,
+) -> impl IntoResponse {
+    accepts.respond(&state.remote_bank.load().bank)
 }
 
-pub async fn search_handler(Json(payload): Json<Token>) -> impl IntoResponse {
-    (StatusCode::OK, payload.token).into_response()
+pub async fn leaderboard_handler(
+    State(state): State<Arc<AppState>>,
+    accepts: Accepts,
+    Query(query): Query<LeaderboardQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let leaderboard = get_leaderboard(state, query).await?;
+
+    Ok(accepts.respond(&leaderboard))
 }
@@ -7,12 +7,53 @@
 //! ## Schema
 //! - Index for all foods
 //! - Fields: name (**string**), votes (**int**), location (**string**, default is "None")
+//! - meals, tags (**string list**): meals serving it today and its dietary traits, from the bank
+//! - served_today (**bool**): served on the day the bank's menus are for, and that day is today
+//! - Filterable: location, meals, tags, served_today. Sortable: votes, name
+//!
+//!
+//!
+//! ## Search
+//! - Any of the requested locations, any of the meals, every tag, see [`FoodQuery::filter`]
+//! - Facet counts per location, meal and tag over every match, not just the returned page
+//! - Returns food ids, the frontend already has the names in the bank
+//! - Sort, highlight and matching strategy are checked against [`SORTS`], [`HIGHLIGHTS`] and [`MATCHING_STRATEGIES`]
+//! - Limit defaults to [`SEARCH_LIMIT`] and is capped by `SEARCH_MAX_LIMIT`, see [`FoodQuery::parse`]
+//! - Offsets past `SEARCH_MAX_OFFSET` are a 400, deep pages never reach Meilisearch
+//! - Highlights are byte ranges of the query in the name, not HTML, the frontend adds its own markup
+//! - Typo tolerance is index wide, `SEARCH_TYPO_ONE_MIN` and `SEARCH_TYPO_TWO_MIN` set the word lengths
+//! - A request may only make it stricter: `typo_min` above `SEARCH_TYPO_ONE_MIN` quotes shorter words,
+//!   which Meilisearch matches exactly, see [`FoodQuery::meili_text`]
+//!
+//!
+//!
+//! ## Rebuild
+//! - Fills a fresh `foods_<unix ms>` index with the bank and Redis votes, settings included
+//! - Swaps it with `foods` once every task finished, then deletes the old index
+//! - Removed or renamed foods disappear and settings changes never reindex the live index
+//! - Runs at startup unless `REBUILD_ON_STARTUP=false`, and from `POST /admin/index/rebuild`
+//! - Leftover staging indexes older than an hour are deleted by the next one, younger ones may
+//!   still be filling on another replica
+//!
+//! ## Settings
+//! - Upserts apply [`init_settings`] to the live index when it has others, see [`update_settings`]
+//! - So an index never rebuilt, e.g. with `REBUILD_ON_STARTUP=false`, still gets its filters and sorts
+//! - The bank's synonyms and stop words follow it the same way
 //!
 //!
 //!
 //! ## Cron Job
-//! - Every 1-5 minutes, we will run through the Redis hash for foods and sync the votes with Meilisearch
-//! - Should just be (name: **string**, votes:**int**)
+//! - Every 5 minutes by default, `vote_sync` runs through the Redis hash for foods and syncs the votes with Meilisearch
+//! - `bank_refresh` upserts too, so menus and served_today follow the new bank
+//! - Both recompute served_today, it turns false at midnight until a bank for the new day is loaded
+//! - Documents are keyed by food id, see [`crate::jobs`]
+//!
+//!
+//!
+//! ## Backend
+//! - Handlers and jobs go through [`SearchBackend`], [`Meilisearch`] is the only real one
+//! - Tests swap in a stub that keeps documents in memory, see `server/tests/common`
+//! - Searches are served from the bank in memory until it is indexed or while it errors, see [`crate::fallback`]
 //!
 //!
 //!
@@ -51,85 +92,708 @@
 //! ```sh
 //! curl -H "Authorization: Bearer $(cat /run/secrets/MEILI_MASTER_KEY)" http://localhost:7700/keys
 //! ```
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
-use bank::foods::Food;
+use async_trait::async_trait;
+use bank::{
+    foods::Bank,
+    payloads::{Highlight, SearchRequest},
+};
 use meilisearch_sdk::{
-    client::Client,
+    client::{Client, SwapIndexes},
+    errors::{Error, ErrorCode, MeilisearchError},
+    search::{MatchingStrategies, Selectors},
     settings::{MinWordSizeForTypos, Settings, TypoToleranceSettings},
+    task_info::TaskInfo,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument, warn};
+
+use crate::{
+    config::Config,
+    error::AppError::{self, InternalError, InvalidParameter},
+    monitor::record_meili,
+    utils::{now_ms, today},
 };
-use serde::Serialize;
 
 pub const FOOD_INDEX: &str = "foods";
+const STAGING_PREFIX: &str = "foods_";
+const STAGING_LABEL: &str = "foods_staging";
+// a rebuild takes seconds, anything older failed halfway
+const STAGING_MAX_AGE_MS: u64 = 60 * 60 * 1000;
 pub const FOOD_ID: &str = "id";
 pub const FOOD_NAME: &str = "name";
 pub const FOOD_VOTES: &str = "votes";
 pub const FOOD_LOCATION: &str = "location";
+pub const FOOD_MEALS: &str = "meals";
+pub const FOOD_TAGS: &str = "tags";
+pub const FOOD_SERVED_TODAY: &str = "served_today";
+const FACETS: [&str; 3] = [FOOD_LOCATION, FOOD_MEALS, FOOD_TAGS];
+pub const SEARCH_LIMIT: usize = 20;
+// allow-lists of SearchRequest, anything else is a 400
+pub const SORTS: [&str; 4] = ["votes:desc", "votes:asc", "name:asc", "name:desc"];
+pub const HIGHLIGHTS: [&str; 1] = [FOOD_NAME];
+pub const MATCHING_STRATEGIES: [&str; 3] = ["last", "all", "frequency"];
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct MeiliFood {
     pub id: u32,
     pub name: String,
-    pub votes: u32,
+    pub votes: i64,
     pub location: String,
+    // missing in documents indexed before they existed
+    #[serde(default)]
+    pub meals: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub served_today: bool,
 }
 
-pub async fn init_meilisearch(
-    meili_url: &str,
-    meili_admin_key: &str,
-    foods_map: &HashMap<String, Food>,
-    food_votes: &HashMap<String, u32>,
-) -> Arc<Client> {
-    let meili_client = Arc::new(Client::new(meili_url, Some(meili_admin_key)).unwrap());
+#[derive(Clone, Default, Debug)]
+pub struct FoodQuery {
+    pub text: String,
+    pub locations: Vec<String>,
+    pub meals: Vec<String>,
+    pub tags: Vec<String>,
+    pub served_today: bool,
+    pub limit: usize,
+    pub offset: usize,
+    // relevance when None
+    pub sort: Option<&'static str>,
+    pub highlight: Vec<&'static str>,
+    pub matching: Option<MatchingStrategies>,
+    // words shorter than this match exactly, 0 leaves typos to the index settings
+    pub typo_min: usize,
+}
 
-    upsert_foods(meili_client.clone(), foods_map, food_votes).await;
+// what a client may ask for, from the config
+#[derive(Clone, Copy, Debug)]
+pub struct SearchBounds {
+    pub max_limit: usize,
+    pub max_offset: usize,
+    pub typo_one_min: u8,
+}
+
+impl From<&Config> for SearchBounds {
+    fn from(config: &Config) -> Self {
+        Self {
+            max_limit: config.search_max_limit,
+            max_offset: config.search_max_offset,
+            typo_one_min: config.search_typo_one_min,
+        }
+    }
+}
 
-    meili_client
+// minimum word lengths for one and two typos
+#[derive(Clone, Copy, Debug)]
+pub struct Typos {
+    pub one_typo: u8,
+    pub two_typos: u8,
 }
 
+// value to number of matching foods, per facet
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Facets {
+    pub locations: HashMap<String, u32>,
+    pub meals: HashMap<String, u32>,
+    pub tags: HashMap<String, u32>,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct FoodResults {
+    pub ids: Vec<u32>,
+    pub total: usize,
+    pub facets: Facets,
+    pub highlights: Vec<Highlight>,
+}
+
+impl FoodQuery {
+    // checks the client's parameters, a limit past max_limit is capped rather than rejected
+    pub fn parse(request: SearchRequest, bounds: SearchBounds) -> Result<Self, AppError> {
+        let sort = match request.sort.as_str() {
+            "" => None,
+            sort => Some(allowed("sort", &SORTS, sort)?),
+        };
+        let highlight = request
+            .highlight
+            .iter()
+            .map(|attribute| allowed("highlight", &HIGHLIGHTS, attribute))
+            .collect::<Result<_, _>>()?;
+        let matching = match request.matching_strategy.as_str() {
+            "" => None,
+            strategy => Some(
+                match allowed("matching_strategy", &MATCHING_STRATEGIES, strategy)? {
+                    "all" => MatchingStrategies::ALL,
+                    "frequency" => MatchingStrategies::FREQUENCY,
+                    _ => MatchingStrategies::LAST,
+                },
+            ),
+        };
+        let limit = match request.limit {
+            0 => SEARCH_LIMIT,
+            limit => limit as usize,
+        };
+        let offset = request.offset as usize;
+        if offset > bounds.max_offset {
+            return Err(InvalidParameter(format!(
+                "offset {offset}, at most {}",
+                bounds.max_offset
+            )));
+        }
+        // at or below the index minimum Meilisearch already matches those words exactly
+        let typo_min = match request.typo_min as usize {
+            typo_min if typo_min > bounds.typo_one_min as usize => typo_min,
+            _ => 0,
+        };
+
+        Ok(Self {
+            text: request.token,
+            locations: request.locations,
+            meals: request.meals,
+            tags: request.tags,
+            served_today: request.served_today,
+            limit: limit.min(bounds.max_limit),
+            offset,
+            sort,
+            highlight,
+            matching,
+            typo_min,
+        })
+    }
+
+    // the query text with words shorter than typo_min quoted, Meilisearch matches a phrase exactly
+    pub fn meili_text(&self) -> String {
+        if self.typo_min == 0 {
+            return self.text.clone();
+        }
+
+        self.text
+            .split_whitespace()
+            .map(|word| word.replace('"', ""))
+            .filter(|word| !word.is_empty())
+            .map(|word| match word.chars().count() < self.typo_min {
+                true => format!("\"{word}\""),
+                false => word,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Meilisearch filter expression, None without filters
+    pub fn filter(&self) -> Option<String> {
+        let mut clauses = Vec::new();
+
+        if !self.locations.is_empty() {
+            clauses.push(format!("{FOOD_LOCATION} IN [{}]", quoted(&self.locations)));
+        }
+        if !self.meals.is_empty() {
+            clauses.push(format!("{FOOD_MEALS} IN [{}]", quoted(&self.meals)));
+        }
+        for tag in &self.tags {
+            clauses.push(format!("{FOOD_TAGS} = {}", quote(tag)));
+        }
+        if self.served_today {
+            clauses.push(format!("{FOOD_SERVED_TODAY} = true"));
+        }
+
+        (!clauses.is_empty()).then(|| clauses.join(" AND "))
+    }
+
+    // the filter applied in memory, the text is not matched
+    pub fn matches(&self, food: &MeiliFood) -> bool {
+        (self.locations.is_empty() || self.locations.contains(&food.location))
+            && (self.meals.is_empty() || food.meals.iter().any(|meal| self.meals.contains(meal)))
+            && self.tags.iter().all(|tag| food.tags.contains(tag))
+            && (!self.served_today || food.served_today)
+    }
+
+    // the sort applied in memory, stable so ties keep their relevance order
+    pub fn order(&self, foods: &mut [&MeiliFood]) {
+        match self.sort {
+            Some("votes:desc") => foods.sort_by_key(|food| -food.votes),
+            Some("votes:asc") => foods.sort_by_key(|food| food.votes),
+            Some("name:asc") => foods.sort_by(|a, b| a.name.cmp(&b.name)),
+            Some("name:desc") => foods.sort_by(|a, b| b.name.cmp(&a.name)),
+            _ => {}
+        }
+    }
+}
+
+fn allowed(
+    parameter: &str,
+    values: &[&'static str],
+    value: &str,
+) -> Result<&'static str, AppError> {
+    values
+        .iter()
+        .find(|allowed| **allowed == value)
+        .copied()
+        .ok_or_else(|| {
+            InvalidParameter(format!(
+                "{parameter} {value:?}, expected one of {}",
+                values.join(", ")
+            ))
+        })
+}
+
+impl Facets {
+    // counts like Meilisearch, once per distinct value of each food
+    pub fn count<'a>(foods: impl IntoIterator<Item = &'a MeiliFood>) -> Self {
+        let mut facets = Facets::default();
+
+        for food in foods {
+            if !food.location.is_empty() {
+                *facets.locations.entry(food.location.clone()).or_default() += 1;
+            }
+            for meal in dedup(&food.meals) {
+                *facets.meals.entry(meal.clone()).or_default() += 1;
+            }
+            for tag in dedup(&food.tags) {
+                *facets.tags.entry(tag.clone()).or_default() += 1;
+            }
+        }
+
+        facets
+    }
+}
+
+fn dedup(values: &[String]) -> impl Iterator<Item = &String> {
+    values
+        .iter()
+        .enumerate()
+        .filter(|(index, value)| !values[..*index].contains(value))
+        .map(|(_, value)| value)
+}
+
+// filter values come from the client, escaped inside double quotes
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn quoted(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| quote(value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // documents and the bank's vocabulary, other settings are kept
+    async fn upsert_foods(
+        &self,
+        bank: &Bank,
+        food_votes: &HashMap<u32, i64>,
+    ) -> Result<(), AppError>;
+
+    // replaces every document and applies the settings
+    async fn rebuild(&self, bank: &Bank, food_votes: &HashMap<u32, i64>) -> Result<(), AppError>;
+
+    async fn food_document(&self, food_id: u32) -> Result<Option<MeiliFood>, AppError>;
+
+    async fn search(&self, query: &FoodQuery) -> Result<FoodResults, AppError>;
+
+    async fn health(&self) -> Result<(), AppError>;
+}
+
+pub struct Meilisearch {
+    client: Arc<Client>,
+    typos: Typos,
+}
+
+impl Meilisearch {
+    pub fn new(meili_url: &str, meili_admin_key: &str, typos: Typos) -> Self {
+        Self {
+            client: init_meilisearch(meili_url, meili_admin_key),
+            typos,
+        }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for Meilisearch {
+    fn name(&self) -> &'static str {
+        "Meilisearch"
+    }
+
+    async fn upsert_foods(
+        &self,
+        bank: &Bank,
+        food_votes: &HashMap<u32, i64>,
+    ) -> Result<(), AppError> {
+        upsert_foods(self.client.clone(), bank, food_votes, self.typos)
+            .await
+            .map_err(|e| InternalError(Box::new(e)))
+    }
+
+    async fn rebuild(&self, bank: &Bank, food_votes: &HashMap<u32, i64>) -> Result<(), AppError> {
+        rebuild_index(self.client.clone(), bank, food_votes, self.typos)
+            .await
+            .map_err(|e| InternalError(Box::new(e)))
+    }
+
+    async fn food_document(&self, food_id: u32) -> Result<Option<MeiliFood>, AppError> {
+        get_food_document(self.client.clone(), food_id)
+            .await
+            .map_err(|e| InternalError(Box::new(e)))
+    }
+
+    async fn search(&self, query: &FoodQuery) -> Result<FoodResults, AppError> {
+        search_foods(self.client.clone(), query)
+            .await
+            .map_err(|e| InternalError(Box::new(e)))
+    }
+
+    async fn health(&self) -> Result<(), AppError> {
+        self.client
+            .health()
+            .await
+            .map(|_| ())
+            .map_err(|e| InternalError(Box::new(e)))
+    }
+}
+
+pub fn init_meilisearch(meili_url: &str, meili_admin_key: &str) -> Arc<Client> {
+    Arc::new(Client::new(meili_url, Some(meili_admin_key)).unwrap())
+}
+
+// documents, plus the settings when the live index has others
+#[instrument(skip_all, fields(foods = bank.foods.len()))]
 pub async fn upsert_foods(
     meili_client: Arc<Client>,
-    foods_map: &HashMap<String, Food>,
-    food_votes: &HashMap<String, u32>,
-) {
-    let meili_foods: Vec<MeiliFood> = foods_map
+    bank: &Bank,
+    food_votes: &HashMap<u32, i64>,
+    typos: Typos,
+) -> Result<(), Error> {
+    let meili_foods = to_meili_foods(bank, food_votes);
+
+    upsert_items(meili_client.clone(), FOOD_INDEX, &meili_foods, FOOD_ID).await?;
+    update_settings(&meili_client, FOOD_INDEX, typos, bank).await?;
+
+    Ok(())
+}
+
+// applies init_settings when the index has others, true when it did
+pub async fn update_settings(
+    meili_client: &Client,
+    index_name: &str,
+    typos: Typos,
+    bank: &Bank,
+) -> Result<bool, Error> {
+    let index = meili_client.index(index_name);
+    let settings = init_settings(typos, bank);
+
+    if same_settings(&index.get_settings().await?, &settings) {
+        return Ok(false);
+    }
+
+    info!(
+        "Updating {index_name} settings with {} synonyms and {} stop words",
+        bank.synonyms.len(),
+        bank.stop_words.len()
+    );
+    let task = index.set_settings(&settings).await?;
+    wait(meili_client, index_name, task).await?;
+
+    Ok(true)
+}
+
+// empty rather than unset, so removed entries are cleared
+fn vocabulary(bank: &Bank) -> Settings {
+    let synonyms: HashMap<&String, &Vec<String>> = bank
+        .synonyms
         .iter()
-        .map(|(name, food)| MeiliFood {
-            id: food.id,
-            name: name.clone(),
-            votes: *food_votes.get(name).unwrap_or(&0),
-            location: food.location.clone(),
-        })
+        .map(|(term, synonyms)| (term, &synonyms.terms))
         .collect();
 
-    meili_client
+    Settings::new()
+        .with_synonyms(synonyms)
+        .with_stop_words(&bank.stop_words)
+}
+
+// Meilisearch keeps the order of neither the vocabulary nor the attribute sets,
+// and fills in typo tolerance fields this server never sets
+fn same_settings(live: &Settings, wanted: &Settings) -> bool {
+    let sorted = |list: &Option<Vec<String>>| {
+        let mut list = list.clone().unwrap_or_default();
+        list.sort();
+        list
+    };
+    let synonyms = |settings: &Settings| {
+        let mut synonyms: Vec<(String, Vec<String>)> = settings
+            .synonyms
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(term, mut synonyms)| {
+                synonyms.sort();
+                (term, synonyms)
+            })
+            .collect();
+        synonyms.sort();
+        synonyms
+    };
+    let typos = |settings: &Settings| {
+        settings
+            .typo_tolerance
+            .as_ref()
+            .map(|typos| (typos.enabled, typos.min_word_size_for_typos.clone()))
+    };
+
+    synonyms(live) == synonyms(wanted)
+        && sorted(&live.stop_words) == sorted(&wanted.stop_words)
+        && sorted(&live.filterable_attributes) == sorted(&wanted.filterable_attributes)
+        && sorted(&live.sortable_attributes) == sorted(&wanted.sortable_attributes)
+        && live.searchable_attributes == wanted.searchable_attributes
+        && live.ranking_rules == wanted.ranking_rules
+        && live.distinct_attribute == wanted.distinct_attribute
+        && typos(live) == typos(wanted)
+}
+
+// fills a fresh index and swaps it with the live one, so searches never see a half built index
+#[instrument(skip_all, fields(foods = bank.foods.len()))]
+pub async fn rebuild_index(
+    meili_client: Arc<Client>,
+    bank: &Bank,
+    food_votes: &HashMap<u32, i64>,
+    typos: Typos,
+) -> Result<(), Error> {
+    delete_staging_indexes(&meili_client).await?;
+
+    let staging = format!("{STAGING_PREFIX}{}", now_ms());
+    info!("Rebuilding {FOOD_INDEX} into {staging}");
+
+    let task = meili_client.create_index(&staging, Some(FOOD_ID)).await?;
+    wait(&meili_client, &staging, task).await?;
+
+    let task = meili_client
+        .index(&staging)
+        .set_settings(&init_settings(typos, bank))
+        .await?;
+    wait(&meili_client, &staging, task).await?;
+
+    let meili_foods = to_meili_foods(bank, food_votes);
+    upsert_items(meili_client.clone(), &staging, &meili_foods, FOOD_ID).await?;
+
+    // swapping needs both sides, the very first rebuild has no live index yet
+    match meili_client.get_index(FOOD_INDEX).await {
+        Ok(_) => {}
+        Err(Error::Meilisearch(MeilisearchError {
+            error_code: ErrorCode::IndexNotFound,
+            ..
+        })) => {
+            let task = meili_client.create_index(FOOD_INDEX, Some(FOOD_ID)).await?;
+            wait(&meili_client, FOOD_INDEX, task).await?;
+        }
+        Err(e) => return Err(e),
+    }
+
+    let task = meili_client
+        .swap_indexes([&SwapIndexes {
+            indexes: (FOOD_INDEX.to_string(), staging.clone()),
+            rename: None,
+        }])
+        .await?;
+    wait(&meili_client, FOOD_INDEX, task).await?;
+
+    // after the swap the staging index holds the old documents
+    let task = meili_client.delete_index(&staging).await?;
+    wait(&meili_client, &staging, task).await?;
+
+    info!("Rebuilt {FOOD_INDEX} with {} foods", meili_foods.len());
+
+    Ok(())
+}
+
+// leftovers of rebuilds that failed halfway, on this replica or another
+async fn delete_staging_indexes(meili_client: &Client) -> Result<(), Error> {
+    let indexes = meili_client.list_all_indexes().await?;
+    let now = now_ms();
+
+    for index in indexes.results {
+        if is_stale_staging(&index.uid, now) {
+            warn!("Deleting leftover index {}", index.uid);
+
+            let task = meili_client.delete_index(&index.uid).await?;
+            wait(meili_client, &index.uid, task).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// staging uids carry their creation time, unknown `foods_*` indexes are left alone
+pub fn is_stale_staging(uid: &str, now_ms: u64) -> bool {
+    uid.strip_prefix(STAGING_PREFIX)
+        .and_then(|created_at_ms| created_at_ms.parse::<u64>().ok())
+        .is_some_and(|created_at_ms| now_ms.saturating_sub(created_at_ms) > STAGING_MAX_AGE_MS)
+}
+
+pub async fn get_food_document(
+    meili_client: Arc<Client>,
+    food_id: u32,
+) -> Result<Option<MeiliFood>, Error> {
+    match meili_client
         .index(FOOD_INDEX)
-        .set_settings(&init_settings())
+        .get_document(&food_id.to_string())
         .await
-        .unwrap();
+    {
+        Ok(document) => Ok(Some(document)),
+        Err(Error::Meilisearch(MeilisearchError {
+            error_code: ErrorCode::DocumentNotFound,
+            ..
+        })) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
 
-    upsert_items(meili_client.clone(), FOOD_INDEX, &meili_foods, FOOD_ID).await;
+#[derive(Deserialize)]
+struct FoodHit {
+    id: u32,
 }
 
-async fn upsert_items<T>(meili_client: Arc<Client>, index_name: &str, items: &[T], id_name: &str)
+#[instrument(skip_all, fields(filtered = query.filter().is_some()))]
+pub async fn search_foods(
+    meili_client: Arc<Client>,
+    query: &FoodQuery,
+) -> Result<FoodResults, Error> {
+    let index = meili_client.index(FOOD_INDEX);
+    let filter = query.filter();
+    let sort = query.sort.map(|sort| [sort]);
+    // highlighted attributes are retrieved too, so their match positions are reported
+    let retrieve: &[&str] = if query.highlight.is_empty() {
+        &[FOOD_ID]
+    } else {
+        &[FOOD_ID, FOOD_NAME]
+    };
+
+    let text = query.meili_text();
+
+    let mut search = index.search();
+    search
+        .with_query(&text)
+        .with_limit(query.limit)
+        .with_offset(query.offset)
+        .with_facets(Selectors::Some(&FACETS))
+        .with_attributes_to_retrieve(Selectors::Some(retrieve))
+        .with_show_matches_position(!query.highlight.is_empty());
+    if let Some(filter) = &filter {
+        search.with_filter(filter);
+    }
+    if let Some(sort) = &sort {
+        search.with_sort(sort);
+    }
+    if let Some(matching) = &query.matching {
+        search.with_matching_strategy(matching.clone());
+    }
+
+    let results = search.execute::<FoodHit>().await?;
+
+    let mut highlights = Vec::new();
+    let mut ids = Vec::with_capacity(results.hits.len());
+    for hit in results.hits {
+        let id = hit.result.id;
+        let mut matches = hit.matches_position.unwrap_or_default();
+
+        for attribute in &query.highlight {
+            for range in matches.remove(*attribute).unwrap_or_default() {
+                highlights.push(Highlight {
+                    id,
+                    attribute: attribute.to_string(),
+                    start: range.start as u32,
+                    length: range.length as u32,
+                });
+            }
+        }
+        ids.push(id);
+    }
+
+    let mut distribution = results.facet_distribution.unwrap_or_default();
+    let mut facet = |name: &str| -> HashMap<String, u32> {
+        distribution
+            .remove(name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(value, count)| (value, count as u32))
+            .collect()
+    };
+
+    Ok(FoodResults {
+        total: results.estimated_total_hits.unwrap_or(ids.len()),
+        ids,
+        facets: Facets {
+            locations: facet(FOOD_LOCATION),
+            meals: facet(FOOD_MEALS),
+            tags: facet(FOOD_TAGS),
+        },
+        highlights,
+    })
+}
+
+#[instrument(skip(meili_client, items), fields(items = items.len()))]
+async fn upsert_items<T>(
+    meili_client: Arc<Client>,
+    index_name: &str,
+    items: &[T],
+    id_name: &str,
+) -> Result<(), Error>
 where
     T: Serialize + Send + Sync,
 {
-    let _result = meili_client
+    let task = meili_client
         .index(index_name)
         .add_or_update(items, Some(id_name))
-        .await
-        .unwrap()
-        .wait_for_completion(&meili_client, None, None)
-        .await
-        .unwrap();
+        .await?;
+
+    wait(&meili_client, index_name, task).await
+}
+
+async fn wait(meili_client: &Client, index_name: &str, task: TaskInfo) -> Result<(), Error> {
+    let started = Instant::now();
+    let result = task.wait_for_completion(meili_client, None, None).await?;
+
+    // one label for every staging index, their names are unique
+    let label = if index_name.starts_with(STAGING_PREFIX) {
+        STAGING_LABEL
+    } else {
+        index_name
+    };
+    record_meili(label, started, result.is_success());
 
     #[cfg(feature = "verbose")]
-    println!("Meili task result: {:?}", _result);
+    println!("Meili task result: {:?}", result);
+
+    if result.is_failure() {
+        return Err(Error::Meilisearch(result.unwrap_failure()));
+    }
+
+    Ok(())
 }
 
-fn init_settings() -> Settings {
-    Settings::new()
+pub fn to_meili_foods(bank: &Bank, food_votes: &HashMap<u32, i64>) -> Vec<MeiliFood> {
+    // a bank from an earlier day says nothing about today
+    let current = bank.served_on == today();
+
+    bank.foods
+        .iter()
+        .map(|(name, food)| MeiliFood {
+            id: food.id,
+            name: name.clone(),
+            votes: *food_votes.get(&food.id).unwrap_or(&0),
+            location: food.location.clone(),
+            meals: food.meals.clone(),
+            tags: food.tags.clone(),
+            served_today: current && !food.location.is_empty(),
+        })
+        .collect()
+}
+
+pub fn init_settings(typos: Typos, bank: &Bank) -> Settings {
+    vocabulary(bank)
         .with_ranking_rules([
             "words",
             "typo",
@@ -139,16 +803,16 @@ fn init_settings() -> Settings {
             "sort",
         ])
         .with_distinct_attribute(Some(FOOD_NAME))
-        .with_filterable_attributes([FOOD_LOCATION])
+        .with_filterable_attributes([FOOD_LOCATION, FOOD_MEALS, FOOD_TAGS, FOOD_SERVED_TODAY])
         .with_searchable_attributes([FOOD_NAME])
-        .with_sortable_attributes([FOOD_VOTES])
+        .with_sortable_attributes([FOOD_VOTES, FOOD_NAME])
         .with_typo_tolerance(TypoToleranceSettings {
             enabled: Some(true),
             disable_on_attributes: None,
             disable_on_words: None,
             min_word_size_for_typos: Some(MinWordSizeForTypos {
-                one_typo: Some(5),
-                two_typos: Some(9),
+                one_typo: Some(typos.one_typo),
+                two_typos: Some(typos.two_typos),
             }),
         })
 }
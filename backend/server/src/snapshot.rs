@@ -0,0 +1,259 @@
+//! # Snapshots
+//!
+//! Durable copies of the votes, neither vote store survives `just clean` or a lost container.
+//!
+//! ## Format
+//! - `payloads.Snapshot` protobuf at `SNAPSHOT_DIR/snapshot_<unix ms>.bin`
+//! - Every food vote total and user bitmap, read in one transaction
+//! - `version` is the snapshot format, `next_food_id` the bank the bitmap positions refer to
+//! - Hourly and daily vote buckets are left out, trending starts over after a restore
+//!
+//! ## Job
+//! - `snapshot` cron job, `SNAPSHOT_SCHEDULE`, hourly by default
+//! - Written to a temporary file first, a crash never leaves a partial snapshot behind
+//! - Only the newest `SNAPSHOT_KEEP` snapshots are kept
+//!
+//! ## Restore
+//! - Only into an empty store: no user bitmaps and every food at 0, so a fresh deploy is fine
+//! - Snapshot format must match, and its bank must not be newer than the remote bank as ids only grow
+//! - Meilisearch catches up on the next `vote_sync`
+//! - The memory store restores the newest snapshot on startup by itself, `--restore` is for Redis
+//! ```sh
+//! docker exec $(docker ps -q -f name=app_rust) /server --restore /snapshots/snapshot_<unix ms>.bin
+//! ```
+use std::{
+    cmp::Reverse,
+    io,
+    path::{Path, PathBuf},
+};
+
+use bank::{
+    RemoteBank, decode_payload, encode_payload, get_remote_bank,
+    payloads::{FoodVotes, Snapshot, UserVotes},
+};
+use thiserror::Error;
+use tokio::fs;
+
+use crate::{
+    config::Config,
+    database::VoteState,
+    error::AppError::{self, InternalError},
+    store::{RedisStore, StoreKind, VoteStore},
+    utils::now_ms,
+};
+
+pub const SNAPSHOT_VERSION: u32 = 1;
+const SNAPSHOT_PREFIX: &str = "snapshot_";
+const SNAPSHOT_EXTENSION: &str = ".bin";
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("{path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("{path}: not a snapshot: {reason}")]
+    Decode { path: PathBuf, reason: String },
+
+    #[error("snapshot format {found} is not supported, expected {SNAPSHOT_VERSION}")]
+    Version { found: u32 },
+
+    #[error("snapshot bank has {snapshot} food ids but the remote bank only {bank}")]
+    NewerBank { snapshot: u32, bank: u32 },
+
+    #[error("the vote store already holds votes, restore only into an empty one")]
+    NotEmpty,
+
+    #[error("the memory store loads the newest snapshot in SNAPSHOT_DIR on startup, copy it there")]
+    MemoryStore,
+
+    #[error("Remote bank: {0}")]
+    Bank(String),
+
+    #[error(transparent)]
+    Store(#[from] AppError),
+}
+
+impl SnapshotError {
+    fn io(path: &Path) -> impl FnOnce(io::Error) -> Self + '_ {
+        move |source| SnapshotError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+fn to_snapshot(vote_state: VoteState, remote_bank: &RemoteBank) -> Snapshot {
+    let mut foods: Vec<FoodVotes> = vote_state
+        .foods
+        .into_iter()
+        .map(|(id, votes)| FoodVotes { id, votes })
+        .collect();
+    foods.sort_unstable_by_key(|food| food.id);
+
+    let mut users: Vec<UserVotes> = vote_state
+        .users
+        .into_iter()
+        .map(|(user, bit_map)| UserVotes { user, bit_map })
+        .collect();
+    users.sort_unstable_by(|a, b| a.user.cmp(&b.user));
+
+    Snapshot {
+        version: SNAPSHOT_VERSION,
+        created_at_ms: now_ms(),
+        next_food_id: remote_bank.bank.next_food_id,
+        foods,
+        users,
+    }
+}
+
+pub async fn take_snapshot(
+    store: &dyn VoteStore,
+    remote_bank: &RemoteBank,
+    config: &Config,
+) -> Result<(PathBuf, Snapshot), SnapshotError> {
+    let vote_state = store.vote_state().await?;
+    let snapshot = to_snapshot(vote_state, remote_bank);
+
+    let dir = Path::new(&config.snapshot_dir);
+    fs::create_dir_all(dir)
+        .await
+        .map_err(SnapshotError::io(dir))?;
+
+    let name = format!(
+        "{SNAPSHOT_PREFIX}{}{SNAPSHOT_EXTENSION}",
+        snapshot.created_at_ms
+    );
+    let path = dir.join(&name);
+    let temporary = dir.join(format!(".{name}.tmp"));
+
+    fs::write(&temporary, encode_payload(&snapshot))
+        .await
+        .map_err(SnapshotError::io(&temporary))?;
+    fs::rename(&temporary, &path)
+        .await
+        .map_err(SnapshotError::io(&path))?;
+
+    prune_snapshots(dir, config.snapshot_keep).await?;
+
+    Ok((path, snapshot))
+}
+
+async fn prune_snapshots(dir: &Path, keep: usize) -> Result<(), SnapshotError> {
+    for path in list_snapshots(dir).await?.into_iter().skip(keep) {
+        fs::remove_file(&path)
+            .await
+            .map_err(SnapshotError::io(&path))?;
+    }
+
+    Ok(())
+}
+
+// newest first
+async fn list_snapshots(dir: &Path) -> Result<Vec<PathBuf>, SnapshotError> {
+    let mut entries = fs::read_dir(dir).await.map_err(SnapshotError::io(dir))?;
+    let mut snapshots = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await.map_err(SnapshotError::io(dir))? {
+        let created_at_ms = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix(SNAPSHOT_PREFIX))
+            .and_then(|name| name.strip_suffix(SNAPSHOT_EXTENSION))
+            .and_then(|created_at_ms| created_at_ms.parse::<u64>().ok());
+
+        if let Some(created_at_ms) = created_at_ms {
+            snapshots.push((created_at_ms, entry.path()));
+        }
+    }
+
+    snapshots.sort_unstable_by_key(|(created_at_ms, _)| Reverse(*created_at_ms));
+
+    Ok(snapshots.into_iter().map(|(_, path)| path).collect())
+}
+
+async fn read_snapshot(path: &Path, remote_bank: &RemoteBank) -> Result<Snapshot, SnapshotError> {
+    let bytes = fs::read(path).await.map_err(SnapshotError::io(path))?;
+    let snapshot: Snapshot =
+        decode_payload(bytes.as_slice()).map_err(|e| SnapshotError::Decode {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        })?;
+
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(SnapshotError::Version {
+            found: snapshot.version,
+        });
+    }
+
+    if snapshot.next_food_id > remote_bank.bank.next_food_id {
+        return Err(SnapshotError::NewerBank {
+            snapshot: snapshot.next_food_id,
+            bank: remote_bank.bank.next_food_id,
+        });
+    }
+
+    Ok(snapshot)
+}
+
+async fn restore_into(store: &dyn VoteStore, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+    // the server fills in foods at 0 on startup, that still counts as empty
+    let current = store.vote_state().await?;
+    if !current.users.is_empty() || current.foods.values().any(|votes| *votes != 0) {
+        return Err(SnapshotError::NotEmpty);
+    }
+
+    let vote_state = VoteState {
+        foods: snapshot
+            .foods
+            .iter()
+            .map(|food| (food.id, food.votes))
+            .collect(),
+        users: snapshot
+            .users
+            .iter()
+            .map(|user| (user.user.clone(), user.bit_map.clone()))
+            .collect(),
+    };
+    store.restore_vote_state(&vote_state).await?;
+
+    Ok(())
+}
+
+// None when there is no snapshot yet, a bad newest snapshot is an error rather than skipped
+pub async fn load_latest_snapshot(
+    store: &dyn VoteStore,
+    config: &Config,
+    remote_bank: &RemoteBank,
+) -> Result<Option<PathBuf>, SnapshotError> {
+    let dir = Path::new(&config.snapshot_dir);
+    if !fs::try_exists(dir).await.map_err(SnapshotError::io(dir))? {
+        return Ok(None);
+    }
+
+    let Some(path) = list_snapshots(dir).await?.into_iter().next() else {
+        return Ok(None);
+    };
+
+    let snapshot = read_snapshot(&path, remote_bank).await?;
+    restore_into(store, &snapshot).await?;
+
+    Ok(Some(path))
+}
+
+pub async fn restore_snapshot(config: &Config, path: &Path) -> Result<Snapshot, SnapshotError> {
+    if config.vote_store == StoreKind::Memory {
+        return Err(SnapshotError::MemoryStore);
+    }
+
+    let remote_bank = get_remote_bank()
+        .await
+        .map_err(|e| SnapshotError::Bank(e.to_string()))?;
+    let snapshot = read_snapshot(path, &remote_bank).await?;
+
+    let store = RedisStore::connect(&config.redis_url)
+        .await
+        .map_err(|e| InternalError(Box::new(e)))?;
+    restore_into(&store, &snapshot).await?;
+
+    Ok(snapshot)
+}
@@ -0,0 +1,25 @@
+//! # Bank Source
+//!
+//! Where the food bank comes from, fetched at startup and by the `bank_refresh` job.
+//!
+//! ## Sources
+//! - [`RemoteSource`]: `bank.bin` from the GitHub repo, what the server always uses
+//! - Tests hand in a fixed bank instead, see `server/tests/common`
+use async_trait::async_trait;
+use bank::{RemoteBank, get_remote_bank};
+
+use crate::error::AppError::{self, InternalError};
+
+#[async_trait]
+pub trait BankSource: Send + Sync {
+    async fn fetch(&self) -> Result<RemoteBank, AppError>;
+}
+
+pub struct RemoteSource;
+
+#[async_trait]
+impl BankSource for RemoteSource {
+    async fn fetch(&self) -> Result<RemoteBank, AppError> {
+        get_remote_bank().await.map_err(|e| InternalError(e.into()))
+    }
+}
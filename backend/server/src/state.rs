@@ -1,41 +1,323 @@
-use std::sync::Arc;
+//! # State
+//!
+//! Shared server state and startup.
+//!
+//! ## Startup
+//! - Remote bank is fetched first, everything else indexes into it, so the server keeps retrying it with the
+//!   same backoff before it starts
+//! - The vote store and Meilisearch are connected in the background with exponential backoff until they succeed
+//! - Past `STARTUP_RETRIES` failed attempts they keep reconnecting every 30s at most, logging each failure
+//! - Meilisearch is rebuilt from scratch and swapped in, see [`crate::search`]
+//! - Until then the server runs degraded: `/bank` and the health endpoints work, votes return 503
+//! - Search is served from the bank in memory until Meilisearch is indexed, or while it errors, see [`crate::fallback`]
+//! - The memory vote store starts from the newest snapshot, see [`crate::store`]
+//! - `/readyz` reports which dependency is still missing and its last connect error
+//!
+//! ## Backends
+//! - Bank source and search backend are handed in through [`Backends`], the vote store follows `VOTE_STORE`
+//! - [`State::with_backends`] skips the background connect and returns the bank error past `STARTUP_RETRIES`,
+//!   tests await [`connect_backends`] themselves
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    future::Future,
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
-use arc_swap::ArcSwap;
-use bank::{RemoteBank, get_remote_bank};
-use meilisearch_sdk::client::Client;
-use redis::aio::ConnectionManager;
+use arc_swap::{ArcSwap, ArcSwapOption};
+use bank::RemoteBank;
+use tokio::{sync::Mutex, time::sleep};
+use tracing::{error, info, warn};
 
-use super::{config::Config, database::init_redis, search::init_meilisearch};
+use super::{
+    config::Config,
+    error::AppError::{self, Conflict, InternalError, Unavailable},
+    fallback::{needs_votes, search_bank},
+    jobs::JobRegistry,
+    memory::MemoryStore,
+    monitor::record_search_fallback,
+    search::{FoodQuery, FoodResults, Meilisearch, SearchBackend, Typos},
+    snapshot::load_latest_snapshot,
+    source::{BankSource, RemoteSource},
+    store::{RedisStore, StoreKind, VoteStore},
+};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+type OpenedStore = (Arc<dyn VoteStore>, HashMap<u32, i64>);
+
+pub struct Backends {
+    pub bank_source: Arc<dyn BankSource>,
+    pub search: Arc<dyn SearchBackend>,
+}
+
+impl Backends {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            bank_source: Arc::new(RemoteSource),
+            search: Arc::new(Meilisearch::new(
+                &config.meili_url,
+                &config.meili_key,
+                Typos {
+                    one_typo: config.search_typo_one_min,
+                    two_typos: config.search_typo_two_min,
+                },
+            )),
+        }
+    }
+}
 
 pub struct State {
     pub remote_bank: ArcSwap<RemoteBank>,
     pub config: Config,
-    pub redis_connection: ConnectionManager,
-    pub meili_client: Arc<Client>,
+    pub bank_source: Arc<dyn BankSource>,
+    pub vote_store: OnceLock<Arc<dyn VoteStore>>,
+    pub search_backend: Arc<dyn SearchBackend>,
+    pub search_ready: AtomicBool,
+    // last connect error of each backend, cleared once it connects
+    pub store_error: ArcSwapOption<String>,
+    pub search_error: ArcSwapOption<String>,
+    pub jobs: JobRegistry,
+    pub index_rebuild: Mutex<()>,
 }
 
 impl State {
-    pub async fn new() -> Arc<Self> {
-        let remote_bank = ArcSwap::from_pointee(get_remote_bank().await.unwrap());
+    pub async fn new(config: Config) -> Arc<Self> {
+        let backends = Backends::from_config(&config);
 
-        let config = Config::load();
+        // nothing to serve without a bank, retried like the other backends instead of exiting
+        let bank_error = ArcSwapOption::empty();
+        let remote_bank = reconnect("Remote bank", config.startup_retries, &bank_error, || {
+            backends.bank_source.fetch()
+        })
+        .await;
+        let state = Self::with_bank(config, backends, remote_bank);
 
-        let (redis_connection, food_votes) =
-            init_redis(&config.redis_url, &remote_bank.load().food_id_to_name).await;
+        tokio::spawn(connect_backends(state.clone()));
 
-        let meili_client = init_meilisearch(
-            &config.meili_url,
-            &config.meili_key,
-            &remote_bank.load().bank.foods,
-            &food_votes,
-        )
-        .await;
+        state
+    }
+
+    // fetches the bank only, the vote store and search stay down until connect_backends
+    pub async fn with_backends(config: Config, backends: Backends) -> Result<Arc<Self>, AppError> {
+        let remote_bank = retry("Remote bank", config.startup_retries, || {
+            backends.bank_source.fetch()
+        })
+        .await?;
 
+        Ok(Self::with_bank(config, backends, remote_bank))
+    }
+
+    fn with_bank(config: Config, backends: Backends, remote_bank: RemoteBank) -> Arc<Self> {
         Arc::new(Self {
-            remote_bank,
+            remote_bank: ArcSwap::from_pointee(remote_bank),
+            bank_source: backends.bank_source,
+            vote_store: OnceLock::new(),
+            search_backend: backends.search,
+            search_ready: AtomicBool::new(false),
+            store_error: ArcSwapOption::empty(),
+            search_error: ArcSwapOption::empty(),
+            jobs: JobRegistry::new(&config),
+            index_rebuild: Mutex::new(()),
             config,
-            redis_connection,
-            meili_client,
         })
     }
+
+    pub fn store(&self) -> Result<Arc<dyn VoteStore>, AppError> {
+        self.vote_store
+            .get()
+            .cloned()
+            .ok_or(Unavailable(self.config.vote_store.name()))
+    }
+
+    pub fn search(&self) -> Result<Arc<dyn SearchBackend>, AppError> {
+        if !self.search_ready.load(Ordering::Acquire) {
+            return Err(Unavailable(self.search_backend.name()));
+        }
+
+        Ok(self.search_backend.clone())
+    }
+
+    // Meilisearch once indexed, the bank in memory before that or when it fails
+    pub async fn search_foods(&self, query: &FoodQuery) -> Result<FoodResults, AppError> {
+        let reason = match self.search() {
+            Ok(search) => match search.search(query).await {
+                Ok(results) => return Ok(results),
+                Err(e) => {
+                    warn!("{} search failed, searching in memory: {e}", search.name());
+                    "failed"
+                }
+            },
+            Err(_) => "not_ready",
+        };
+        record_search_fallback(reason);
+
+        // without a store every food has 0 votes, the results are still served
+        let mut food_votes = HashMap::new();
+        if needs_votes(query)
+            && let Ok(store) = self.store()
+        {
+            food_votes = store.food_votes().await.unwrap_or_default();
+        }
+
+        Ok(search_bank(&self.remote_bank.load(), &food_votes, query))
+    }
+
+    // also brings search back if Meilisearch never came up at startup
+    pub async fn rebuild_search(&self, food_votes: &HashMap<u32, i64>) -> Result<usize, AppError> {
+        let _rebuilding = self
+            .index_rebuild
+            .try_lock()
+            .map_err(|_| Conflict("index rebuild already running".to_string()))?;
+
+        let remote_bank = self.remote_bank.load_full();
+        self.search_backend
+            .rebuild(&remote_bank.bank, food_votes)
+            .await?;
+
+        self.search_ready.store(true, Ordering::Release);
+
+        Ok(remote_bank.bank.foods.len())
+    }
+}
+
+pub async fn connect_backends(state: Arc<State>) {
+    let remote_bank = state.remote_bank.load_full();
+    let retries = state.config.startup_retries;
+
+    let store_name = state.config.vote_store.name();
+    let search_name = state.search_backend.name();
+
+    // without votes, indexing would reset every document to 0
+    state
+        .search_error
+        .store(Some(Arc::new(format!("Waiting for {store_name}"))));
+
+    let (store, food_votes) = reconnect(store_name, retries, &state.store_error, || {
+        open_store(&state, &remote_bank)
+    })
+    .await;
+    let _ = state.vote_store.set(store);
+
+    // copied into every attempt
+    let (state, bank, food_votes) = (&state, &remote_bank.bank, &food_votes);
+    reconnect(search_name, retries, &state.search_error, || async move {
+        // an admin rebuild may have brought search up in the meantime
+        if state.search_ready.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        if state.config.rebuild_on_startup {
+            return state.rebuild_search(food_votes).await.map(|_| ());
+        }
+
+        state.search_backend.upsert_foods(bank, food_votes).await
+    })
+    .await;
+    state.search_ready.store(true, Ordering::Release);
+}
+
+async fn open_store(state: &State, remote_bank: &RemoteBank) -> Result<OpenedStore, AppError> {
+    let store: Arc<dyn VoteStore> = match state.config.vote_store {
+        StoreKind::Redis => Arc::new(
+            RedisStore::connect(&state.config.redis_url)
+                .await
+                .map_err(|e| InternalError(Box::new(e)))?,
+        ),
+        StoreKind::Memory => {
+            let store = MemoryStore::new();
+
+            // starting empty would let the snapshot job overwrite the good snapshots
+            match load_latest_snapshot(&store, &state.config, remote_bank)
+                .await
+                .map_err(|e| InternalError(Box::new(e)))?
+            {
+                Some(path) => info!("Loaded votes from {}", path.display()),
+                None => warn!(
+                    "No snapshot in {}, starting without votes",
+                    state.config.snapshot_dir
+                ),
+            }
+
+            Arc::new(store)
+        }
+    };
+
+    let food_votes = store.populate(&remote_bank.food_id_to_name).await?;
+
+    Ok((store, food_votes))
+}
+
+pub async fn retry<T, E, F, Fut>(name: &str, attempts: u32, mut operation: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+
+    loop {
+        match operation().await {
+            Ok(value) => {
+                info!("{name} ready after {attempt} attempt(s)");
+                return Ok(value);
+            }
+            Err(e) if attempt >= attempts => {
+                error!("{name} failed after {attempt} attempt(s): {e}");
+                return Err(e);
+            }
+            Err(e) => {
+                warn!("{name} attempt {attempt}/{attempts} failed: {e}, retrying in {backoff:?}");
+
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+// like retry but never gives up, the last error is kept for readiness until it succeeds
+async fn reconnect<T, E, F, Fut>(
+    name: &str,
+    attempts: u32,
+    last_error: &ArcSwapOption<String>,
+    mut operation: F,
+) -> T
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+
+    loop {
+        match operation().await {
+            Ok(value) => {
+                info!("{name} ready after {attempt} attempt(s)");
+                last_error.store(None);
+                return value;
+            }
+            Err(e) => {
+                if attempt == attempts {
+                    error!(
+                        "{name} failed after {attempt} attempt(s): {e}, reconnecting in the background"
+                    );
+                } else {
+                    warn!("{name} attempt {attempt} failed: {e}, retrying in {backoff:?}");
+                }
+                last_error.store(Some(Arc::new(e.to_string())));
+
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                attempt += 1;
+            }
+        }
+    }
 }
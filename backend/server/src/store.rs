@@ -0,0 +1,214 @@
+//! # Vote Store
+//!
+//! Storage behind votes, user bitmaps, rate limits and the audit log, handlers only talk to [`VoteStore`].
+//!
+//! ## Backends
+//! - `redis` (default): Lua scripts in [`crate::database`], shared by every replica
+//! - `memory`: in-process maps in [`crate::memory`], for small single instance deployments and tests
+//! - Select with `VOTE_STORE`
+//!
+//! ## Memory
+//! - Same rules as the Lua scripts: votes never drop below 0, hourly buckets, compaction, token buckets
+//! - Gone on restart, so the newest snapshot in `SNAPSHOT_DIR` is loaded at startup, see [`crate::snapshot`]
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use async_trait::async_trait;
+use redis::{RedisError, aio::ConnectionManager, cmd};
+use serde::Serialize;
+
+use crate::{
+    database::{
+        Bucket, Vote, VoteChange, VoteState, change_votes, compact_votes, connect_redis, get_audit,
+        get_daily_history, get_food_votes, get_hourly_history, get_user_votes, get_vote_deltas,
+        get_vote_state, get_votes, populate_foods, push_audit, restore_vote_state,
+        store_user_votes, take_tokens, update_foods,
+    },
+    error::AppError::{self, InternalError},
+};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreKind {
+    #[default]
+    Redis,
+    Memory,
+}
+
+impl StoreKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            StoreKind::Redis => "Redis",
+            StoreKind::Memory => "Memory",
+        }
+    }
+}
+
+impl Display for StoreKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for StoreKind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "redis" => Ok(StoreKind::Redis),
+            "memory" => Ok(StoreKind::Memory),
+            _ => Err("expected redis or memory".to_string()),
+        }
+    }
+}
+
+#[async_trait]
+pub trait VoteStore: Send + Sync {
+    // adds every food of the bank at 0 votes without touching existing ones, returns all votes
+    async fn populate(&self, food_id_to_name: &[String]) -> Result<HashMap<u32, i64>, AppError>;
+
+    // applies votes that keep the food at 0 or above, also into the current hourly bucket,
+    // NotFound without applying any when a food has no votes entry, see populate
+    async fn update_foods(&self, votes: &[(isize, Vote)]) -> Result<(), AppError>;
+
+    async fn food_votes(&self) -> Result<HashMap<u32, i64>, AppError>;
+
+    async fn votes(&self, food_id: u32) -> Result<Option<i64>, AppError>;
+
+    // sums the hourly buckets of the last `hours` hours, including the current one
+    async fn vote_deltas(&self, hours: u64) -> Result<HashMap<u32, i64>, AppError>;
+
+    // returns (before, after), None if the food has no votes entry
+    async fn change_votes(
+        &self,
+        food_id: u32,
+        change: VoteChange,
+    ) -> Result<Option<(i64, i64)>, AppError>;
+
+    // (hours since epoch, vote delta) for the last `hours` hours, oldest first, 0 once compacted
+    async fn hourly_history(&self, food_id: u32, hours: u64) -> Result<Vec<(u64, i64)>, AppError>;
+
+    // (days since epoch, vote delta) for the last `days` days, oldest first, compacted or not
+    async fn daily_history(&self, food_id: u32, days: u64) -> Result<Vec<(u64, i64)>, AppError>;
+
+    // rolls hourly buckets older than the retention into days, returns hours compacted
+    async fn compact_votes(&self, retention_hours: u64) -> Result<usize, AppError>;
+
+    async fn user_votes(&self, user_id: &str) -> Result<Option<Vec<u8>>, AppError>;
+
+    async fn set_user_votes(&self, user_id: &str, bit_map: &[u8]) -> Result<(), AppError>;
+
+    async fn vote_state(&self) -> Result<VoteState, AppError>;
+
+    async fn restore_vote_state(&self, vote_state: &VoteState) -> Result<(), AppError>;
+
+    // takes one token from every bucket or none at all, returning ms to wait (0 if allowed)
+    async fn take_tokens(&self, buckets: &[Bucket]) -> Result<u64, AppError>;
+
+    async fn push_audit(&self, entry: &str) -> Result<(), AppError>;
+
+    // newest first
+    async fn audit(&self, count: usize) -> Result<Vec<String>, AppError>;
+
+    async fn ping(&self) -> Result<(), AppError>;
+}
+
+pub struct RedisStore {
+    connection_manager: ConnectionManager,
+}
+
+impl RedisStore {
+    pub async fn connect(redis_url: &str) -> Result<Self, RedisError> {
+        Ok(Self {
+            connection_manager: connect_redis(redis_url).await?,
+        })
+    }
+
+    fn connection(&self) -> ConnectionManager {
+        self.connection_manager.clone()
+    }
+}
+
+#[async_trait]
+impl VoteStore for RedisStore {
+    async fn populate(&self, food_id_to_name: &[String]) -> Result<HashMap<u32, i64>, AppError> {
+        populate_foods(food_id_to_name, &mut self.connection())
+            .await
+            .map_err(|e| InternalError(Box::new(e)))
+    }
+
+    async fn update_foods(&self, votes: &[(isize, Vote)]) -> Result<(), AppError> {
+        update_foods(&mut self.connection(), votes).await
+    }
+
+    async fn food_votes(&self) -> Result<HashMap<u32, i64>, AppError> {
+        get_food_votes(&mut self.connection()).await
+    }
+
+    async fn votes(&self, food_id: u32) -> Result<Option<i64>, AppError> {
+        get_votes(&mut self.connection(), food_id).await
+    }
+
+    async fn vote_deltas(&self, hours: u64) -> Result<HashMap<u32, i64>, AppError> {
+        get_vote_deltas(&mut self.connection(), hours).await
+    }
+
+    async fn change_votes(
+        &self,
+        food_id: u32,
+        change: VoteChange,
+    ) -> Result<Option<(i64, i64)>, AppError> {
+        change_votes(&mut self.connection(), food_id, change).await
+    }
+
+    async fn hourly_history(&self, food_id: u32, hours: u64) -> Result<Vec<(u64, i64)>, AppError> {
+        get_hourly_history(&mut self.connection(), food_id, hours).await
+    }
+
+    async fn daily_history(&self, food_id: u32, days: u64) -> Result<Vec<(u64, i64)>, AppError> {
+        get_daily_history(&mut self.connection(), food_id, days).await
+    }
+
+    async fn compact_votes(&self, retention_hours: u64) -> Result<usize, AppError> {
+        compact_votes(&mut self.connection(), retention_hours).await
+    }
+
+    async fn user_votes(&self, user_id: &str) -> Result<Option<Vec<u8>>, AppError> {
+        get_user_votes(&mut self.connection(), user_id).await
+    }
+
+    async fn set_user_votes(&self, user_id: &str, bit_map: &[u8]) -> Result<(), AppError> {
+        store_user_votes(&mut self.connection(), user_id, bit_map).await
+    }
+
+    async fn vote_state(&self) -> Result<VoteState, AppError> {
+        get_vote_state(&mut self.connection()).await
+    }
+
+    async fn restore_vote_state(&self, vote_state: &VoteState) -> Result<(), AppError> {
+        restore_vote_state(&mut self.connection(), vote_state).await
+    }
+
+    async fn take_tokens(&self, buckets: &[Bucket]) -> Result<u64, AppError> {
+        take_tokens(&mut self.connection(), buckets).await
+    }
+
+    async fn push_audit(&self, entry: &str) -> Result<(), AppError> {
+        push_audit(&mut self.connection(), entry).await
+    }
+
+    async fn audit(&self, count: usize) -> Result<Vec<String>, AppError> {
+        get_audit(&mut self.connection(), count).await
+    }
+
+    async fn ping(&self) -> Result<(), AppError> {
+        cmd("PING")
+            .query_async::<String>(&mut self.connection())
+            .await
+            .map(|_| ())
+            .map_err(|e| InternalError(Box::new(e)))
+    }
+}
@@ -0,0 +1,133 @@
+//! # Telemetry
+//!
+//! Tracing spans, printed by the `fmt` layer and optionally exported over OTLP.
+//!
+//! ## Spans
+//! - `request`: every HTTP request with method, route, status and a hash of the `valid_id` cookie
+//! - `update_foods`: the Redis vote script
+//! - `upsert_foods` / `upsert_items`: Meilisearch settings and document tasks
+//! - `cron`: every cron job run with the job name
+//!
+//! ## Exporter
+//! - Set `OTLP_ENDPOINT` (gRPC, e.g. `http://otel-collector:4317`) to export spans, unset disables it
+//! - The exporter is attached after the config loads, so config logs still print before it
+//! - Local collector in `monitor/docker.monitor.yml`
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    Resource,
+    trace::{SdkTracer, SdkTracerProvider},
+};
+use sha2::{Digest, Sha256};
+use tracing::{Instrument, field::Empty, info, info_span, warn};
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::{
+    EnvFilter, Registry, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt,
+};
+
+use crate::utils::{USER_COOKIE, get_cookie};
+
+const SERVICE_NAME: &str = "server";
+
+type OtlpLayer = OpenTelemetryLayer<Registry, SdkTracer>;
+
+pub struct Telemetry {
+    handle: reload::Handle<Option<OtlpLayer>, Registry>,
+    provider: Option<SdkTracerProvider>,
+}
+
+pub fn init_tracing() -> Telemetry {
+    let (otlp_layer, handle) = reload::Layer::new(None);
+
+    tracing_subscriber::registry()
+        .with(otlp_layer)
+        .with(EnvFilter::from_default_env())
+        .with(fmt::layer())
+        .init();
+
+    Telemetry {
+        handle,
+        provider: None,
+    }
+}
+
+impl Telemetry {
+    pub fn enable_otlp(&mut self, endpoint: Option<&str>) {
+        let Some(endpoint) = endpoint else {
+            info!("OTLP_ENDPOINT not set, not exporting spans");
+            return;
+        };
+
+        let exporter = match SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()
+        {
+            Ok(exporter) => exporter,
+            Err(e) => {
+                warn!("Failed to build OTLP exporter: {}", e);
+                return;
+            }
+        };
+
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+            .build();
+
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME));
+        if let Err(e) = self.handle.reload(Some(layer)) {
+            warn!("Failed to attach OTLP layer: {}", e);
+            return;
+        }
+
+        info!("Exporting spans to {endpoint}");
+        self.provider = Some(provider);
+    }
+
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider
+            && let Err(e) = provider.shutdown()
+        {
+            warn!("Failed to flush spans: {}", e);
+        }
+    }
+}
+
+pub async fn trace_requests(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let user = get_cookie(request.headers(), USER_COOKIE)
+        .map(hash_user)
+        .unwrap_or_default();
+
+    let span = info_span!(
+        "request",
+        method = %request.method(),
+        route,
+        user,
+        status = Empty,
+    );
+
+    let response = next.run(request).instrument(span.clone()).await;
+    span.record("status", response.status().as_u16());
+
+    response
+}
+
+// ids never leave the server, only a short stable hash to group a user's requests
+fn hash_user(user_id: &str) -> String {
+    hex::encode(&Sha256::digest(user_id.as_bytes())[..8])
+}
+
+pub fn cron_span(job: &'static str) -> tracing::Span {
+    info_span!("cron", job)
+}
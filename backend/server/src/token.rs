@@ -0,0 +1,109 @@
+//! # Token Verification
+//!
+//! HMAC signed timestamp cookie, the same check rpxy performs, so hitting the server port directly
+//! does not bypass it.
+//!
+//! ## Format
+//! - Cookie `token`: `<unix ms>.<hex HMAC-SHA256 of the unix ms>` signed with the `JWT_KEY` secret,
+//!   as minted by the frontend's +server.ts
+//!
+//! ## Checks
+//! - Missing or badly signed token: 401
+//! - Older than `TOKEN_EXPIRY_MS` (5 minutes): 401
+//! - Younger than `TOKEN_DEBOUNCE_MS` (search debounce time): 429
+//!
+//! ## Refresh
+//! - A token stamped with the current time is issued for an `X-refresh` request,
+//!   or once the token is older than `TOKEN_REFRESH_MS` (4 minutes, the frontend's timer)
+//! - Any other request keeps its cookie
+//!
+//! ## Notes
+//! - Disable with `VERIFY_TOKEN=false` when running behind rpxy
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, header::SET_COOKIE},
+    middleware::Next,
+    response::Response,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{
+    error::AppError::{self, Debounced, ExpiredToken, InvalidToken},
+    state::State as AppState,
+    utils::{get_cookie, now_ms},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const TOKEN_COOKIE: &str = "token";
+const REFRESH_HEADER: &str = "x-refresh";
+
+pub async fn verify_token(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Some(key) = &state.config.token_key else {
+        return Ok(next.run(request).await);
+    };
+
+    let issued_ms = get_cookie(request.headers(), TOKEN_COOKIE)
+        .and_then(|token| verify(key, token))
+        .ok_or(InvalidToken)?;
+
+    let age = now_ms().saturating_sub(issued_ms);
+    if age > state.config.token_expiry_ms {
+        return Err(ExpiredToken);
+    }
+    if age < state.config.token_debounce_ms {
+        return Err(Debounced);
+    }
+
+    let refresh =
+        request.headers().contains_key(REFRESH_HEADER) || age >= state.config.token_refresh_ms;
+
+    let mut response = next.run(request).await;
+    if !refresh {
+        return Ok(response);
+    }
+
+    // stamped at the end of the request life time
+    let cookie = format!(
+        "{TOKEN_COOKIE}={}; Path=/; Max-Age={}; HttpOnly; Secure; SameSite=Strict",
+        sign(key, now_ms()),
+        state.config.token_expiry_ms / 1000
+    );
+    if let Ok(value) = HeaderValue::from_str(&cookie) {
+        response.headers_mut().append(SET_COOKIE, value);
+    }
+
+    Ok(response)
+}
+
+pub fn sign(key: &str, issued_ms: u64) -> String {
+    let timestamp = issued_ms.to_string();
+
+    let mut mac = new_mac(key);
+    mac.update(timestamp.as_bytes());
+
+    format!("{timestamp}.{}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// the issue time of a correctly signed token
+pub fn verify(key: &str, token: &str) -> Option<u64> {
+    let (timestamp, signature) = token.split_once('.')?;
+    let signature = hex::decode(signature).ok()?;
+
+    let mut mac = new_mac(key);
+    mac.update(timestamp.as_bytes());
+    mac.verify_slice(&signature).ok()?;
+
+    timestamp.parse().ok()
+}
+
+fn new_mac(key: &str) -> HmacSha256 {
+    HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length")
+}
@@ -5,6 +5,7 @@
 //! ## Overall Payloads
 //!
 //! Responses/requests between the frontend and backend.
+//! Types, encode/decode helpers and the route manifest are generated into `schema/` by `cargo codegen`.
 //!
 //! ### Verification
 //! Cookies
@@ -17,7 +18,11 @@
 //!
 //! ### Fetch/Update User Votes
 //! - Protobuf, N bits bitmap representing user votes
+//! - Food id `i` is bit `i % 8` (least significant first) of byte `i / 8`, see [`bank::bitmap`] and the generated `schema/bitmap.ts`
 //! - Just flip the respective bits and it will overwrite whatever we had for the user
+//! - Send `next_food_id` of the bank the bitmaps were built against, bitmaps are `next_food_id / 8` bytes rounded up
+//! - Older banks are fine, foods added since count as not voted
+//! - A bank newer than the server's gets 409 with `X-Bank-Version: <server next_food_id>`, re-fetch `/bank` and retry
 //!
 //! If no valid_id cookie
 //! - Append username string to protobuf
@@ -36,11 +41,15 @@
 //!
 //! ### Search/Filter Votes
 //! To backend
-//! - Protobuf, 4 bits representing which is here, query string, location filter u8 enum, sort by bit flag to indicate direction, paiganation u32 number
+//! - `SearchRequest`: query text, any of these locations, any of these meals, every dietary tag, only foods served today
+//! - Optional sort (`votes:desc`, `name:asc`, ...), limit and offset for paging, attributes to highlight, matching strategy
+//! - Optional `typo_min`, words shorter than it match exactly, it can only be stricter than the server's setting
 //!
 //! From backend
-//! - Protobuf, bitmap representing which food is here, order in bitmap represents order of foods
-//! - list of u32s, each is a vote count
+//! - `SearchResponse`: matching food ids in rank order and the total match count
+//! - Facet counts per location, meal and tag over every match, to label the filters
+//! - Highlight byte ranges per returned food, marked up by the frontend
+//! - Unknown sorts, highlights or strategies and offsets past the server's maximum are a 400 `invalid_parameter`
 //!
 //!
 //!
@@ -1,75 +1,158 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use axum::body::Bytes;
-use bank::{get_votes_from_bytes, payloads::Votes};
+use axum::http::{HeaderMap, header::COOKIE};
+use bank::{bitmap::FoodBitmap, get_votes_from_bytes, payloads::Votes};
+use chrono::Local;
+#[cfg(feature = "verbose")]
+use tracing::info;
 
 use crate::{
     database::Vote,
-    error::AppError::{self, MalformedPayload},
+    error::AppError::{self, MalformedPayload, TooManyVotes, UnknownBank},
     state::State,
 };
 
-pub fn get_maps(state: Arc<State>, bytes: Bytes) -> Result<Votes, AppError> {
-    let vote_maps = get_votes_from_bytes(bytes).map_err(|_| AppError::MalformedPayload)?;
+pub const USER_COOKIE: &str = "valid_id";
+
+// (food id, vote) for every bit that changed between the old and new bitmap
+pub type VoteFlips = Vec<(isize, Vote)>;
+
+// checks the payload against the bank, returns the client's next_food_id
+pub fn check_maps(food_id_to_name: &[String], vote_maps: &Votes) -> Result<u32, AppError> {
+    let server = food_id_to_name.len() as u32;
+
+    // clients from before the field was sent are assumed to be current
+    let client = match vote_maps.next_food_id {
+        0 => server,
+        client => client,
+    };
+    // ids only grow, a bank ahead of ours is one this server has not loaded yet
+    if client > server {
+        return Err(UnknownBank { client, server });
+    }
 
     if vote_maps.old_bit_map.len() != vote_maps.new_bit_map.len()
-        || vote_maps.old_bit_map.len() > state.remote_bank.load().food_id_to_name.len()
+        || vote_maps.old_bit_map.len() > FoodBitmap::byte_len(client)
     {
-        return Err(AppError::MalformedPayload);
+        return Err(MalformedPayload);
     }
 
-    Ok(vote_maps)
+    Ok(client)
 }
 
-fn compare_bits(
-    state: Arc<State>,
-    votes: &mut Vec<(isize, Vote)>,
-    old_byte: u8,
-    new_byte: u8,
-    byte_index: usize,
-) -> Result<(), AppError> {
-    for bit_index in 0..8 {
-        // remember bits are right to left, so seventh index bit is first byte, leftmost bit
-        // 0th index bit is the first bit on the right of the first byte
-        let old_bit = (old_byte >> bit_index) & 1;
-        let new_bit = (new_byte >> bit_index) & 1;
-
-        if old_bit == new_bit {
-            continue;
-        }
+// untrusted protobuf bytes to votes without touching state, property tested and fuzzed in server/fuzz
+pub fn diff_votes(
+    food_id_to_name: &[String],
+    alias_to_kept: &HashMap<u32, u32>,
+    max_vote_flips: usize,
+    body: &[u8],
+) -> Result<(VoteFlips, Vec<u8>), AppError> {
+    let vote_maps = get_votes_from_bytes(body).map_err(|_| MalformedPayload)?;
+
+    diff_vote_maps(food_id_to_name, alias_to_kept, max_vote_flips, vote_maps)
+}
 
-        #[cfg(feature = "verbose")]
-        info!("Bit changed at byte {}, bit {}", byte_index, bit_index);
+// decoded payload to votes, whichever format it came in
+pub fn diff_vote_maps(
+    food_id_to_name: &[String],
+    alias_to_kept: &HashMap<u32, u32>,
+    max_vote_flips: usize,
+    vote_maps: Votes,
+) -> Result<(VoteFlips, Vec<u8>), AppError> {
+    let client = check_maps(food_id_to_name, &vote_maps)?;
 
-        let vote = match (old_bit, new_bit) {
-            (1, 0) => Vote::Decrement,
-            (0, 1) => Vote::Increment,
-            _ => return Err(MalformedPayload),
-        };
+    // bits past the client's bank are padding, foods it does not know about stay 0
+    let mut old = FoodBitmap::from_bytes(vote_maps.old_bit_map);
+    let mut new = FoodBitmap::from_bytes(vote_maps.new_bit_map);
+    old.resize(client);
+    new.resize(client);
 
-        let food_index = byte_index * 8 + bit_index;
-        if let Some(name) = state.remote_bank.load().food_id_to_name.get(food_index) {
-            if !name.is_empty() {
-                votes.push((food_index as isize, vote));
+    // an alias bit is a vote for its kept food, both set still count once
+    for (alias_id, kept_id) in alias_to_kept {
+        for bit_map in [&mut old, &mut new] {
+            if bit_map.get(*alias_id) {
+                bit_map.set(*alias_id, false);
+                bit_map.set(*kept_id, true);
             }
         }
     }
 
-    Ok(())
+    // removed foods are ignored
+    let votes: VoteFlips = old
+        .diff(&new)
+        .filter(|(id, _)| {
+            food_id_to_name
+                .get(*id as usize)
+                .is_some_and(|name| !name.is_empty())
+        })
+        .map(|(id, set)| {
+            let vote = if set {
+                Vote::Increment
+            } else {
+                Vote::Decrement
+            };
+            (id as isize, vote)
+        })
+        .collect();
+
+    #[cfg(feature = "verbose")]
+    info!("Flipped votes: {}", votes.len());
+
+    // the caller knows how long the bucket takes to refill
+    if votes.len() > max_vote_flips {
+        return Err(TooManyVotes(votes.len(), max_vote_flips, 0));
+    }
+
+    // stored in the layout of the current bank
+    new.resize(food_id_to_name.len() as u32);
+
+    Ok((votes, new.into_bytes()))
 }
 
-pub fn get_votes_from_body(state: Arc<State>, body: Bytes) -> Result<Vec<(isize, Vote)>, AppError> {
-    let vote_maps = get_maps(state.clone(), body)?;
-    let mut votes = Vec::new();
+// returns the flipped votes and the user's new bitmap
+pub fn get_votes_from_body(
+    state: Arc<State>,
+    vote_maps: Votes,
+) -> Result<(VoteFlips, Vec<u8>), AppError> {
+    let remote_bank = state.remote_bank.load();
+
+    diff_vote_maps(
+        &remote_bank.food_id_to_name,
+        &remote_bank.alias_to_kept,
+        state.config.max_vote_flips,
+        vote_maps,
+    )
+    .map_err(|e| match e {
+        TooManyVotes(flips, max, _) => {
+            TooManyVotes(flips, max, state.config.ip_rate_refill_ms.div_ceil(1000))
+        }
+        e => e,
+    })
+}
 
-    for (byte_index, (&old_byte, &new_byte)) in vote_maps
-        .old_bit_map
+pub fn get_cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
         .iter()
-        .zip(vote_maps.new_bit_map.iter())
-        .enumerate()
-    {
-        compare_bits(state.clone(), &mut votes, old_byte, new_byte, byte_index)?;
-    }
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+// YYYY-MM-DD in local time, the format process writes to the bank's served_on
+pub fn today() -> String {
+    Local::now().date_naive().format("%Y-%m-%d").to_string()
+}
 
-    Ok(votes)
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
 }
@@ -0,0 +1,78 @@
+mod common;
+
+use std::sync::atomic::Ordering;
+
+use axum::http::{Request, StatusCode};
+use common::TestServer;
+use serde_json::Value;
+
+async fn admin_json(server: &TestServer, uri: &str) -> (StatusCode, Value) {
+    let (status, body) = server.admin(Request::get(uri)).await;
+
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn history_reports_votes_per_hour_and_day() {
+    let server = TestServer::start().await;
+    server.vote(None, &[0b000], &[0b001]).await;
+
+    let (status, history) = admin_json(&server, "/admin/foods/0/history?count=3").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(history["bucket"], "hour");
+
+    let points = history["points"].as_array().unwrap();
+    let deltas: Vec<i64> = points
+        .iter()
+        .map(|point| point["delta"].as_i64().unwrap())
+        .collect();
+    // the vote may have landed in the hour before, should the hour just have turned
+    assert_eq!(deltas.len(), 3);
+    assert_eq!(deltas[0], 0);
+    assert_eq!(deltas.iter().sum::<i64>(), 1);
+    assert_eq!(
+        points[2]["start_ms"].as_u64().unwrap() - points[1]["start_ms"].as_u64().unwrap(),
+        60 * 60 * 1000
+    );
+
+    // days merge the hours not compacted yet
+    let (_, history) = admin_json(&server, "/admin/foods/Apple/history?bucket=day&count=2").await;
+    let deltas: Vec<i64> = history["points"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|point| point["delta"].as_i64().unwrap())
+        .collect();
+    assert_eq!(deltas.iter().sum::<i64>(), 1);
+
+    let (_, history) = admin_json(&server, "/admin/foods/1/history?bucket=day&count=2").await;
+    assert_eq!(history["points"][0]["delta"], 0);
+    assert_eq!(history["points"][1]["delta"], 0);
+}
+
+#[tokio::test]
+async fn history_rejects_unknown_foods_and_buckets() {
+    let server = TestServer::start().await;
+
+    let (status, _) = admin_json(&server, "/admin/foods/99/history").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = admin_json(&server, "/admin/foods/0/history?bucket=week").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn food_report_survives_search_being_down() {
+    let server = TestServer::start().await;
+    server.vote(None, &[0b000], &[0b001]).await;
+
+    let (_, report) = admin_json(&server, "/admin/foods/0").await;
+    assert_eq!(report["document"]["id"], 0);
+
+    server.state.search_ready.store(false, Ordering::Release);
+    let (status, report) = admin_json(&server, "/admin/foods/0").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["name"], "Apple");
+    assert_eq!(report["votes"], 1);
+    assert!(report["document"].is_null());
+}
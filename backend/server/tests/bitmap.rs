@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use bank::{bitmap::FoodBitmap, encode_payload, payloads::Votes};
+use proptest::{collection::vec, prelude::*};
+use server::{
+    database::Vote,
+    error::AppError,
+    utils::{VoteFlips, diff_votes},
+};
+
+const NO_CAP: usize = usize::MAX;
+
+fn body(old: &[u8], new: &[u8]) -> Vec<u8> {
+    versioned_body(0, old, new)
+}
+
+fn versioned_body(next_food_id: u32, old: &[u8], new: &[u8]) -> Vec<u8> {
+    encode_payload(&Votes {
+        old_bit_map: old.to_vec(),
+        new_bit_map: new.to_vec(),
+        next_food_id,
+    })
+}
+
+// bitmap as the server stores it, fit to its bank
+fn stored(names: &[String], bit_map: &[u8]) -> Vec<u8> {
+    let mut bit_map = FoodBitmap::from_bytes(bit_map.to_vec());
+    bit_map.resize(names.len() as u32);
+
+    bit_map.into_bytes()
+}
+
+fn count(votes: &VoteFlips, vote: Vote) -> usize {
+    votes
+        .iter()
+        .filter(|(_, flipped)| flipped.as_str() == vote.as_str())
+        .count()
+}
+
+// food names, roughly one in four ids removed from the bank
+fn bank() -> impl Strategy<Value = Vec<String>> {
+    vec(prop::bool::weighted(0.75), 1..80).prop_map(|present| {
+        present
+            .iter()
+            .enumerate()
+            .map(|(id, present)| {
+                if *present {
+                    format!("food {id}")
+                } else {
+                    String::new()
+                }
+            })
+            .collect()
+    })
+}
+
+// equal length bitmaps within the byte limit of the bank
+fn bank_and_maps() -> impl Strategy<Value = (Vec<String>, Vec<u8>, Vec<u8>)> {
+    bank().prop_flat_map(|names| {
+        let bytes = FoodBitmap::byte_len(names.len() as u32);
+        (0..=bytes).prop_flat_map(move |length| {
+            (
+                Just(names.clone()),
+                vec(any::<u8>(), length),
+                vec(any::<u8>(), length),
+            )
+        })
+    })
+}
+
+proptest! {
+    #[test]
+    fn flips_match_the_popcount_of_xor(old in vec(any::<u8>(), 0..8), new in vec(any::<u8>(), 0..8)) {
+        let length = old.len().min(new.len());
+        let (old, new) = (&old[..length], &new[..length]);
+        // every bit maps to a food
+        let names: Vec<String> = (0..length * 8).map(|id| format!("food {id}")).collect();
+
+        let (votes, _) = diff_votes(&names, &HashMap::new(), NO_CAP, &body(old, new)).unwrap();
+
+        let popcount = |f: fn(u8, u8) -> u8| -> usize {
+            old.iter().zip(new).map(|(o, n)| f(*o, *n).count_ones() as usize).sum()
+        };
+        prop_assert_eq!(votes.len(), popcount(|o, n| o ^ n));
+        prop_assert_eq!(count(&votes, Vote::Increment), popcount(|o, n| !o & n));
+        prop_assert_eq!(count(&votes, Vote::Decrement), popcount(|o, n| o & !n));
+    }
+
+    #[test]
+    fn only_foods_in_the_bank_are_voted((names, old, new) in bank_and_maps()) {
+        let (votes, bit_map) = diff_votes(&names, &HashMap::new(), NO_CAP, &body(&old, &new)).unwrap();
+        let (old_bits, new_bits) = (FoodBitmap::from_bytes(old.clone()), FoodBitmap::from_bytes(new.clone()));
+
+        let expected = (0..old.len() as u32 * 8)
+            .filter(|id| names.get(*id as usize).is_some_and(|name| !name.is_empty()))
+            .filter(|id| old_bits.get(*id) != new_bits.get(*id))
+            .count();
+        prop_assert_eq!(votes.len(), expected);
+
+        for (id, vote) in &votes {
+            prop_assert!(!names[*id as usize].is_empty());
+
+            let (old_bit, new_bit) = (old_bits.get(*id as u32), new_bits.get(*id as u32));
+            prop_assert_eq!(vote.as_str(), new_bit as isize - old_bit as isize);
+        }
+
+        // ascending and each food at most once
+        prop_assert!(votes.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        prop_assert_eq!(bit_map, stored(&names, &new));
+    }
+
+    #[test]
+    fn older_banks_vote_only_for_foods_they_know(
+        (names, client, old, new) in bank().prop_flat_map(|names| {
+            let server = names.len() as u32;
+            (1..=server).prop_flat_map(move |client| {
+                let bytes = FoodBitmap::byte_len(client);
+                (Just(names.clone()), Just(client), vec(any::<u8>(), bytes), vec(any::<u8>(), bytes))
+            })
+        })
+    ) {
+        let (votes, bit_map) = diff_votes(&names, &HashMap::new(), NO_CAP, &versioned_body(client, &old, &new)).unwrap();
+
+        prop_assert!(votes.iter().all(|(id, _)| (*id as u32) < client));
+        prop_assert_eq!(bit_map.len(), FoodBitmap::byte_len(names.len() as u32));
+
+        // foods added since the client's bank read as not voted
+        let bit_map = FoodBitmap::from_bytes(bit_map);
+        prop_assert!(bit_map.iter_ones().all(|id| id < client));
+    }
+
+    #[test]
+    fn future_banks_are_rejected(names in bank(), ahead in 1..100u32) {
+        let client = names.len() as u32 + ahead;
+
+        let result = diff_votes(&names, &HashMap::new(), NO_CAP, &versioned_body(client, &[0], &[1]));
+        let rejected = matches!(
+            result,
+            Err(AppError::UnknownBank { client: found, server }) if found == client && server == names.len() as u32
+        );
+        prop_assert!(rejected);
+    }
+
+    #[test]
+    fn maps_longer_than_the_bank_are_rejected(names in bank(), extra in 1..8usize) {
+        let length = FoodBitmap::byte_len(names.len() as u32) + extra;
+        let maps = vec![0xff; length];
+
+        let result = diff_votes(&names, &HashMap::new(), NO_CAP, &body(&vec![0; length], &maps));
+        prop_assert!(matches!(result, Err(AppError::MalformedPayload)));
+    }
+
+    #[test]
+    fn maps_of_different_lengths_are_rejected(old in vec(any::<u8>(), 0..8), new in vec(any::<u8>(), 0..8)) {
+        prop_assume!(old.len() != new.len());
+        let names: Vec<String> = (0..64).map(|id| format!("food {id}")).collect();
+
+        let result = diff_votes(&names, &HashMap::new(), NO_CAP, &body(&old, &new));
+        prop_assert!(matches!(result, Err(AppError::MalformedPayload)));
+    }
+
+    #[test]
+    fn flips_above_the_cap_are_rejected((names, old, new) in bank_and_maps(), cap in 0..16usize) {
+        let flips = diff_votes(&names, &HashMap::new(), NO_CAP, &body(&old, &new)).unwrap().0.len();
+
+        match diff_votes(&names, &HashMap::new(), cap, &body(&old, &new)) {
+            Ok((votes, _)) => prop_assert!(votes.len() <= cap),
+            Err(AppError::TooManyVotes(found, max, _)) => {
+                prop_assert_eq!((found, max), (flips, cap));
+                prop_assert!(flips > cap);
+            }
+            Err(e) => prop_assert!(false, "unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn arbitrary_bytes_never_panic(bytes in vec(any::<u8>(), 0..256)) {
+        let names: Vec<String> = (0..64).map(|id| format!("food {id}")).collect();
+
+        let _ = diff_votes(&names, &HashMap::new(), NO_CAP, &bytes);
+    }
+}
@@ -0,0 +1,316 @@
+//! Server built from in-process fakes: a fixed bank, the memory vote store and a stub search backend.
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use async_trait::async_trait;
+use axum::{
+    body::{Body, Bytes},
+    http::{Request, StatusCode},
+};
+use bank::{
+    RemoteBank, encode_payload,
+    foods::{Bank, Food},
+    payloads::{Highlight, Votes},
+};
+use http_body_util::BodyExt;
+use server::{
+    config::Config,
+    error::AppError,
+    router,
+    search::{FOOD_NAME, Facets, FoodQuery, FoodResults, MeiliFood, SearchBackend, to_meili_foods},
+    source::BankSource,
+    state::{Backends, State, connect_backends},
+    utils::today,
+};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+pub const ADMIN_KEY: &str = "test-admin";
+
+pub struct FakeBank {
+    bank: Mutex<Bank>,
+}
+
+impl FakeBank {
+    pub fn new(bank: Bank) -> Self {
+        Self {
+            bank: Mutex::new(bank),
+        }
+    }
+
+    // served by the next fetch, e.g. the bank_refresh job
+    pub fn set(&self, bank: Bank) {
+        *self.bank.lock().unwrap() = bank;
+    }
+}
+
+#[async_trait]
+impl BankSource for FakeBank {
+    async fn fetch(&self) -> Result<RemoteBank, AppError> {
+        RemoteBank::new(self.bank.lock().unwrap().clone())
+            .map_err(|e| AppError::InternalError(e.into()))
+    }
+}
+
+#[derive(Default)]
+pub struct StubSearch {
+    documents: Mutex<HashMap<u32, MeiliFood>>,
+    pub rebuilds: AtomicUsize,
+    // searches error like an unreachable Meilisearch
+    pub failing: AtomicBool,
+    // indexing errors too, like a Meilisearch that never came up
+    pub down: AtomicBool,
+}
+
+impl StubSearch {
+    pub fn votes(&self, food_id: u32) -> Option<i64> {
+        self.documents
+            .lock()
+            .unwrap()
+            .get(&food_id)
+            .map(|document| document.votes)
+    }
+
+    pub fn document(&self, food_id: u32) -> Option<MeiliFood> {
+        self.documents.lock().unwrap().get(&food_id).cloned()
+    }
+
+    pub fn rebuilds(&self) -> usize {
+        self.rebuilds.load(Ordering::Acquire)
+    }
+}
+
+#[async_trait]
+impl SearchBackend for StubSearch {
+    fn name(&self) -> &'static str {
+        "Stub search"
+    }
+
+    async fn upsert_foods(
+        &self,
+        bank: &Bank,
+        food_votes: &HashMap<u32, i64>,
+    ) -> Result<(), AppError> {
+        if self.down.load(Ordering::Acquire) {
+            return Err(AppError::InternalError("stub search is down".into()));
+        }
+
+        self.documents.lock().unwrap().extend(
+            to_meili_foods(bank, food_votes)
+                .into_iter()
+                .map(|document| (document.id, document)),
+        );
+
+        Ok(())
+    }
+
+    async fn rebuild(&self, bank: &Bank, food_votes: &HashMap<u32, i64>) -> Result<(), AppError> {
+        self.documents.lock().unwrap().clear();
+        self.rebuilds.fetch_add(1, Ordering::AcqRel);
+
+        self.upsert_foods(bank, food_votes).await
+    }
+
+    async fn food_document(&self, food_id: u32) -> Result<Option<MeiliFood>, AppError> {
+        Ok(self.documents.lock().unwrap().get(&food_id).cloned())
+    }
+
+    // case insensitive substring of the name, most voted first unless sorted
+    async fn search(&self, query: &FoodQuery) -> Result<FoodResults, AppError> {
+        if self.failing.load(Ordering::Acquire) {
+            return Err(AppError::InternalError("stub search is failing".into()));
+        }
+
+        let documents = self.documents.lock().unwrap();
+        let text = query.text.to_lowercase();
+
+        let mut matches: Vec<&MeiliFood> = documents
+            .values()
+            .filter(|food| food.name.to_lowercase().contains(&text) && query.matches(food))
+            .collect();
+        matches.sort_by_key(|food| (-food.votes, food.id));
+        query.order(&mut matches);
+
+        let page: Vec<&MeiliFood> = matches
+            .iter()
+            .skip(query.offset)
+            .take(query.limit)
+            .copied()
+            .collect();
+        let highlights = page
+            .iter()
+            .filter(|_| !text.is_empty() && query.highlight.contains(&FOOD_NAME))
+            .filter_map(|food| {
+                Some(Highlight {
+                    id: food.id,
+                    attribute: FOOD_NAME.to_string(),
+                    start: food.name.to_lowercase().find(&text)? as u32,
+                    length: text.len() as u32,
+                })
+            })
+            .collect();
+
+        Ok(FoodResults {
+            ids: page.iter().map(|food| food.id).collect(),
+            total: matches.len(),
+            facets: Facets::count(matches),
+            highlights,
+        })
+    }
+
+    async fn health(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+}
+
+pub struct TestServer {
+    pub state: Arc<State>,
+    pub bank: Arc<FakeBank>,
+    pub search: Arc<StubSearch>,
+    pub snapshots: TempDir,
+}
+
+impl TestServer {
+    // connected and indexed, like a server past its startup retries
+    pub async fn start() -> Self {
+        Self::start_with("").await
+    }
+
+    pub async fn start_with(extra: &str) -> Self {
+        Self::start_in(TempDir::new().unwrap(), extra).await
+    }
+
+    // reuses a snapshot dir, the memory store restores the newest snapshot in it
+    pub async fn start_in(snapshots: TempDir, extra: &str) -> Self {
+        let server = Self::build(snapshots, extra).await;
+        connect_backends(server.state.clone()).await;
+
+        server
+    }
+
+    // bank only, the vote store and search are still down
+    pub async fn build(snapshots: TempDir, extra: &str) -> Self {
+        // tokens are off unless a test turns them on, TOML rejects the key twice
+        let verify_token = match extra.contains("verify_token") {
+            true => "",
+            false => "verify_token = false",
+        };
+        let config = Config::from_toml(&format!(
+            "meili_key = \"test\"\n\
+             vote_store = \"memory\"\n\
+             {verify_token}\n\
+             admin_key = \"{ADMIN_KEY}\"\n\
+             startup_retries = 1\n\
+             snapshot_dir = '{}'\n\
+             {extra}",
+            snapshots.path().display()
+        ))
+        .unwrap();
+
+        let bank = Arc::new(FakeBank::new(test_bank()));
+        let search = Arc::new(StubSearch::default());
+
+        let state = State::with_backends(
+            config,
+            Backends {
+                bank_source: bank.clone(),
+                search: search.clone(),
+            },
+        )
+        .await
+        .unwrap();
+
+        Self {
+            state,
+            bank,
+            search,
+            snapshots,
+        }
+    }
+
+    pub async fn send(&self, request: Request<Body>) -> (StatusCode, Bytes) {
+        let response = router(self.state.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, body)
+    }
+
+    pub async fn get(&self, uri: &str) -> (StatusCode, Bytes) {
+        self.send(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+    }
+
+    pub async fn vote(&self, user: Option<&str>, old: &[u8], new: &[u8]) -> StatusCode {
+        let mut request = Request::post("/votes");
+        if let Some(user) = user {
+            request = request.header("cookie", format!("valid_id={user}"));
+        }
+
+        let body = votes_body(old, new);
+        self.send(request.body(Body::from(body)).unwrap()).await.0
+    }
+
+    pub async fn admin(&self, request: axum::http::request::Builder) -> (StatusCode, Bytes) {
+        let request = request
+            .header("authorization", format!("Bearer {ADMIN_KEY}"))
+            .body(Body::empty())
+            .unwrap();
+
+        self.send(request).await
+    }
+
+    pub async fn votes(&self, food_id: u32) -> Option<i64> {
+        self.state.store().unwrap().votes(food_id).await.unwrap()
+    }
+}
+
+// Apple 0 and Curry 2 at Ford, Bread 1 at Wiley
+// menus for today, Bread is not served
+pub fn test_bank() -> Bank {
+    let food = |id, location: &str, meals: &[&str], tags: &[&str]| Food {
+        id,
+        location: location.to_string(),
+        meals: meals.iter().map(|meal| meal.to_string()).collect(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+    };
+
+    Bank {
+        next_food_id: 3,
+        next_location_id: 2,
+        foods: HashMap::from([
+            (
+                "Apple".to_string(),
+                food(0, "Ford", &["Breakfast", "Lunch"], &["Vegan"]),
+            ),
+            ("Bread".to_string(), food(1, "", &[], &["Vegan"])),
+            (
+                "Curry".to_string(),
+                food(2, "Ford", &["Dinner"], &["Spicy", "Vegan"]),
+            ),
+        ]),
+        locations: HashMap::from([("Ford".to_string(), 0), ("Wiley".to_string(), 1)]),
+        served_on: today(),
+        ..Default::default()
+    }
+}
+
+// without next_food_id, like clients from before it was sent
+pub fn votes_body(old: &[u8], new: &[u8]) -> Vec<u8> {
+    versioned_votes_body(0, old, new)
+}
+
+pub fn versioned_votes_body(next_food_id: u32, old: &[u8], new: &[u8]) -> Vec<u8> {
+    encode_payload(&Votes {
+        old_bit_map: old.to_vec(),
+        new_bit_map: new.to_vec(),
+        next_food_id,
+    })
+}
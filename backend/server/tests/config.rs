@@ -0,0 +1,125 @@
+use std::{env, fs};
+
+use server::config::{Config, ConfigError};
+use tempfile::TempDir;
+
+const SECRETS: [&str; 3] = ["meili-secret", "token-secret", "admin-secret"];
+
+fn errors(toml: &str) -> Vec<ConfigError> {
+    match Config::from_toml(toml) {
+        Ok(_) => panic!("config should not load:\n{toml}"),
+        Err(errors) => errors.0,
+    }
+}
+
+#[test]
+fn every_invalid_key_is_reported_at_once() {
+    let errors = errors(
+        "meili_key = \"test\"\n\
+         port = \"http\"\n\
+         vote_store = \"sqlite\"\n\
+         search_typo_one_min = 9\n\
+         search_typo_two_min = 5\n\
+         cleanup_schedule = \"never\"",
+    );
+
+    let keys: Vec<&str> = errors
+        .iter()
+        .filter_map(|error| match error {
+            ConfigError::Parse { key, .. } | ConfigError::Invalid { key, .. } => Some(*key),
+            _ => None,
+        })
+        .collect();
+    for key in [
+        "port",
+        "vote_store",
+        "search_typo_one_min",
+        "cleanup_schedule",
+    ] {
+        assert!(keys.contains(&key), "{key} in {errors:?}");
+    }
+}
+
+#[test]
+fn missing_secrets_and_unknown_keys_are_rejected() {
+    let errors = errors("verify_token = true\nprot = 2000");
+
+    assert!(errors.iter().any(|error| matches!(
+        error,
+        ConfigError::Missing {
+            key: "meili_key",
+            ..
+        }
+    )));
+    assert!(errors.iter().any(|error| matches!(
+        error,
+        ConfigError::Missing {
+            key: "token_key",
+            ..
+        }
+    )));
+    assert!(
+        errors
+            .iter()
+            .any(|error| matches!(error, ConfigError::Unknown { key } if key == "prot"))
+    );
+}
+
+#[test]
+fn printed_and_debug_output_redact_secrets() {
+    let config = Config::from_toml(&format!(
+        "meili_key = \"{}\"\n\
+         verify_token = true\n\
+         token_key = \"{}\"\n\
+         admin_key = \"{}\"",
+        SECRETS[0], SECRETS[1], SECRETS[2]
+    ))
+    .unwrap();
+    assert_eq!(config.admin_key.as_deref(), Some(SECRETS[2]));
+
+    for output in [config.to_toml(), format!("{config:?}")] {
+        for secret in SECRETS {
+            assert!(!output.contains(secret), "{secret} in\n{output}");
+        }
+        assert_eq!(output.matches("<redacted>").count(), 3, "{output}");
+        assert!(output.contains("verify_token = true\n"), "{output}");
+        assert!(output.contains("token_key = \"<redacted>\"\n"), "{output}");
+    }
+}
+
+// the only test reading the environment, the others go through from_toml
+#[test]
+fn environment_overrides_the_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    fs::write(
+        &path,
+        "meili_key = \"file-key\"\n\
+         verify_token = false\n\
+         port = 2000\n\
+         max_vote_flips = 8",
+    )
+    .unwrap();
+
+    // SAFETY: no other test in this binary reads or writes the environment
+    unsafe {
+        env::set_var("CONFIG_PATH", &path);
+        env::set_var("RUST_PORT", "3000");
+        env::set_var("MEILI_ADMIN_KEY", "env-key");
+    }
+
+    let config = Config::load().unwrap();
+    assert_eq!(config.port, 3000);
+    assert_eq!(config.meili_key, "env-key");
+    // keys without a variable set keep the file value
+    assert_eq!(config.max_vote_flips, 8);
+    assert!(config.to_toml().contains("verify_token = false\n"));
+
+    // a bad variable is reported like a bad file value
+    unsafe { env::set_var("RUST_PORT", "not a port") };
+    let errors = Config::load().unwrap_err().0;
+    assert!(matches!(
+        errors.as_slice(),
+        [ConfigError::Parse { key: "port", .. }]
+    ));
+}
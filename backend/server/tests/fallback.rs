@@ -0,0 +1,204 @@
+mod common;
+
+use std::collections::HashMap;
+
+use bank::{
+    RemoteBank,
+    foods::{Bank, Food},
+};
+use meilisearch_sdk::search::MatchingStrategies;
+use server::{
+    fallback::{needs_votes, search_bank},
+    search::{FOOD_NAME, FoodQuery, FoodResults, SEARCH_LIMIT},
+};
+
+fn menu() -> RemoteBank {
+    let names = [
+        "Mac and Cheese",
+        "Macaroni Salad",
+        "Chicken Curry",
+        "Curry Rice",
+        "Grilled Chicken",
+    ];
+    let foods = names
+        .iter()
+        .enumerate()
+        .map(|(id, name)| {
+            let food = Food {
+                id: id as u32,
+                location: if id % 2 == 0 { "Ford" } else { "Wiley" }.to_string(),
+                ..Default::default()
+            };
+            (name.to_string(), food)
+        })
+        .collect();
+
+    RemoteBank::new(Bank {
+        next_food_id: names.len() as u32,
+        foods,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn query(text: &str) -> FoodQuery {
+    FoodQuery {
+        text: text.to_string(),
+        limit: SEARCH_LIMIT,
+        ..Default::default()
+    }
+}
+
+fn search(query: &FoodQuery) -> FoodResults {
+    search_bank(&menu(), &HashMap::new(), query)
+}
+
+#[test]
+fn empty_query_returns_everything_in_id_order() {
+    let results = search(&query(""));
+
+    assert_eq!(results.ids, vec![0, 1, 2, 3, 4]);
+    assert_eq!(results.total, 5);
+}
+
+#[test]
+fn exact_words_rank_above_prefixes() {
+    // Macaroni Salad only matches by prefix
+    assert_eq!(search(&query("mac")).ids, vec![0, 1]);
+
+    // shorter names first between equal matches
+    assert_eq!(search(&query("curry")).ids, vec![3, 2]);
+}
+
+#[test]
+fn typos_match_through_trigrams() {
+    assert_eq!(search(&query("chiken")).ids, vec![2, 4]);
+    assert!(search(&query("pizza")).ids.is_empty());
+}
+
+#[test]
+fn matching_strategy_decides_partial_matches() {
+    // more matched words rank first
+    assert_eq!(search(&query("chicken curry")).ids, vec![2, 3, 4]);
+
+    let all = FoodQuery {
+        matching: Some(MatchingStrategies::ALL),
+        ..query("chicken curry")
+    };
+    assert_eq!(search(&all).ids, vec![2]);
+}
+
+#[test]
+fn filters_pages_and_facets_follow_the_query() {
+    let filtered = FoodQuery {
+        locations: vec!["Ford".to_string()],
+        ..query("")
+    };
+    let results = search(&filtered);
+    assert_eq!(results.ids, vec![0, 2, 4]);
+    assert_eq!(
+        results.facets.locations,
+        HashMap::from([("Ford".to_string(), 3)])
+    );
+
+    let paged = FoodQuery {
+        offset: 1,
+        limit: 2,
+        ..query("")
+    };
+    let results = search(&paged);
+    assert_eq!(results.ids, vec![1, 2]);
+    assert_eq!(results.total, 5);
+}
+
+#[test]
+fn votes_sort_uses_the_given_votes() {
+    let sorted = FoodQuery {
+        sort: Some("votes:desc"),
+        ..query("chicken")
+    };
+    assert!(needs_votes(&sorted));
+    assert!(!needs_votes(&query("chicken")));
+
+    let votes = HashMap::from([(4, 10), (2, 3)]);
+    assert_eq!(search_bank(&menu(), &votes, &sorted).ids, vec![4, 2]);
+}
+
+#[test]
+fn highlights_are_byte_ranges_of_matched_words() {
+    let highlighted = FoodQuery {
+        highlight: vec![FOOD_NAME],
+        ..query("chick cury")
+    };
+    let results = search(&highlighted);
+
+    let ranges: Vec<(u32, u32, u32)> = results
+        .highlights
+        .iter()
+        .map(|highlight| (highlight.id, highlight.start, highlight.length))
+        .collect();
+    // Chicken Curry, then the prefix of Grilled Chicken above the typo in Curry Rice
+    assert_eq!(results.ids, vec![2, 4, 3]);
+    assert_eq!(ranges, vec![(2, 0, 5), (2, 8, 5), (4, 8, 5), (3, 0, 5)]);
+}
+
+#[test]
+fn short_words_match_exactly_under_typo_min() {
+    assert_eq!(search(&query("cury")).ids, vec![3, 2]);
+
+    let strict = FoodQuery {
+        typo_min: 6,
+        ..query("cury")
+    };
+    assert!(search(&strict).ids.is_empty());
+
+    let strict = FoodQuery {
+        typo_min: 6,
+        ..query("curry")
+    };
+    assert_eq!(search(&strict).ids, vec![3, 2]);
+}
+
+#[test]
+fn accented_names_match_their_own_query() {
+    let foods = ["Crème Brûlée", "Éclair"]
+        .iter()
+        .enumerate()
+        .map(|(id, name)| {
+            let food = Food {
+                id: id as u32,
+                ..Default::default()
+            };
+            (name.to_string(), food)
+        })
+        .collect();
+    let bank = RemoteBank::new(Bank {
+        next_food_id: 2,
+        foods,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let search = |text: &str| {
+        let highlighted = FoodQuery {
+            highlight: vec![FOOD_NAME],
+            ..query(text)
+        };
+        let results = search_bank(&bank, &HashMap::new(), &highlighted);
+        let ranges: Vec<(u32, u32, u32)> = results
+            .highlights
+            .iter()
+            .map(|highlight| (highlight.id, highlight.start, highlight.length))
+            .collect();
+
+        (results.ids, ranges)
+    };
+
+    // byte ranges of the original names, accented letters are two bytes
+    assert_eq!(search("écl"), (vec![1], vec![(1, 0, 4)]));
+    assert_eq!(search("ÉCLAIR"), (vec![1], vec![(1, 0, 7)]));
+    assert_eq!(
+        search("CRÈME brûlée"),
+        (vec![0], vec![(0, 0, 6), (0, 7, 8)])
+    );
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use axum::http::{Request, StatusCode};
+use bank::foods::{Bank, Food};
+use common::{TestServer, test_bank};
+use server::jobs::{JobKind, Outcome, run_job};
+
+#[tokio::test]
+async fn vote_sync_copies_votes_into_search() {
+    let server = TestServer::start().await;
+
+    server.vote(None, &[0b000], &[0b001]).await;
+    assert_eq!(server.search.votes(0), Some(0));
+
+    let status = run_job(&server.state, JobKind::VoteSync).await.unwrap();
+    assert!(matches!(
+        status.last_run.unwrap().outcome,
+        Outcome::Succeeded
+    ));
+    assert_eq!(server.search.votes(0), Some(1));
+}
+
+#[tokio::test]
+async fn bank_refresh_loads_the_new_bank() {
+    let server = TestServer::start().await;
+
+    let mut bank = test_bank();
+    bank.next_food_id = 4;
+    bank.foods.insert(
+        "Dill Pickle".to_string(),
+        Food {
+            id: 3,
+            location: "Wiley".to_string(),
+            ..Default::default()
+        },
+    );
+    server.bank.set(bank);
+
+    run_job(&server.state, JobKind::BankRefresh).await.unwrap();
+    assert_eq!(server.state.remote_bank.load().bank.foods.len(), 4);
+
+    // the new food id is now within the bitmap and counted by the store
+    assert_eq!(
+        server.vote(None, &[0b0000], &[0b1000]).await,
+        StatusCode::OK
+    );
+    assert_eq!(server.votes(3).await, Some(1));
+    assert_eq!(server.search.votes(3), Some(0));
+}
+
+#[tokio::test]
+async fn bank_refresh_updates_served_today() {
+    let server = TestServer::start().await;
+    let served_today = |id| {
+        server
+            .search
+            .document(id)
+            .is_some_and(|food| food.served_today)
+    };
+    assert!(served_today(0));
+    assert!(!served_today(1));
+
+    // menus from another day
+    server.bank.set(Bank {
+        served_on: "2000-01-01".to_string(),
+        ..test_bank()
+    });
+    run_job(&server.state, JobKind::BankRefresh).await.unwrap();
+
+    assert!(!served_today(0));
+    assert!(!served_today(2));
+}
+
+#[tokio::test]
+async fn cleanup_runs_against_the_store() {
+    let server = TestServer::start().await;
+
+    let status = run_job(&server.state, JobKind::Cleanup).await.unwrap();
+    assert_eq!(status.runs, 1);
+    assert!(matches!(
+        status.last_run.unwrap().outcome,
+        Outcome::Succeeded
+    ));
+}
+
+#[tokio::test]
+async fn snapshots_restore_into_a_fresh_memory_store() {
+    let server = TestServer::start().await;
+
+    server.vote(Some("dana"), &[0b000], &[0b101]).await;
+    run_job(&server.state, JobKind::Snapshot).await.unwrap();
+
+    let restarted = TestServer::start_in(server.snapshots, "").await;
+    assert_eq!(restarted.votes(0).await, Some(1));
+    assert_eq!(restarted.votes(2).await, Some(1));
+
+    let bit_map = restarted.state.store().unwrap().user_votes("dana").await;
+    assert_eq!(bit_map.unwrap(), Some(vec![0b101]));
+}
+
+#[tokio::test]
+async fn admin_runs_jobs_with_the_admin_key() {
+    let server = TestServer::start().await;
+
+    let (status, _) = server
+        .admin(Request::post("/admin/jobs/vote_sync/run"))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let anonymous = Request::post("/admin/jobs/vote_sync/run")
+        .body(Default::default())
+        .unwrap();
+    assert_eq!(server.send(anonymous).await.0, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = server.admin(Request::post("/admin/jobs/nope/run")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::TestServer;
+use server::manifest::{Auth, ROUTES};
+
+#[tokio::test]
+async fn every_manifest_route_is_routed() {
+    let server = TestServer::start().await;
+
+    for route in ROUTES {
+        let path = route
+            .path
+            .replace("{name}", "vote_sync")
+            .replace("{key}", "0")
+            .replace("{user}", "alice");
+        let request = Request::builder()
+            .method(route.method)
+            .uri(&path)
+            .body(Body::empty())
+            .unwrap();
+
+        let (status, _) = server.send(request).await;
+        assert_ne!(status, StatusCode::NOT_FOUND, "{} {path}", route.method);
+        assert_ne!(
+            status,
+            StatusCode::METHOD_NOT_ALLOWED,
+            "{} {path}",
+            route.method
+        );
+
+        if route.auth == Auth::Admin {
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{} {path}", route.method);
+        }
+    }
+}
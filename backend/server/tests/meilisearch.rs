@@ -0,0 +1,186 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+};
+use bank::foods::{Bank, Synonyms};
+use meilisearch_sdk::client::Client;
+use serde_json::{Map, Value, json};
+use server::search::{FOOD_INDEX, Typos, is_stale_staging, update_settings};
+use tokio::net::TcpListener;
+
+const TIME: &str = "2026-01-01T00:00:00Z";
+const TYPOS: Typos = Typos {
+    one_typo: 4,
+    two_typos: 8,
+};
+
+// settings of one index and every settings body it received, updates finish as soon as they are enqueued
+#[derive(Default)]
+struct FakeMeili {
+    settings: Map<String, Value>,
+    patches: Vec<Map<String, Value>>,
+}
+
+type Shared = Arc<Mutex<FakeMeili>>;
+
+async fn get_settings(State(meili): State<Shared>) -> Json<Value> {
+    Json(Value::Object(meili.lock().unwrap().settings.clone()))
+}
+
+// like Meilisearch, fields missing from the body keep their value
+async fn patch_settings(
+    State(meili): State<Shared>,
+    Path(index): Path<String>,
+    Json(body): Json<Map<String, Value>>,
+) -> (StatusCode, Json<Value>) {
+    let mut meili = meili.lock().unwrap();
+    meili.settings.extend(body.clone());
+    meili.patches.push(body);
+
+    let task = json!({
+        "taskUid": meili.patches.len(),
+        "indexUid": index,
+        "status": "enqueued",
+        "type": "settingsUpdate",
+        "enqueuedAt": TIME,
+    });
+    (StatusCode::ACCEPTED, Json(task))
+}
+
+async fn get_task(Path(uid): Path<u32>) -> Json<Value> {
+    Json(json!({
+        "uid": uid,
+        "indexUid": FOOD_INDEX,
+        "status": "succeeded",
+        "type": "settingsUpdate",
+        "details": null,
+        "duration": "PT0.001S",
+        "enqueuedAt": TIME,
+        "startedAt": TIME,
+        "finishedAt": TIME,
+        "canceledBy": null,
+        "error": null,
+    }))
+}
+
+async fn fake_meilisearch() -> (Client, Shared) {
+    let meili = Shared::default();
+    let router = Router::new()
+        .route(
+            "/indexes/{index}/settings",
+            get(get_settings).patch(patch_settings),
+        )
+        .route("/tasks/{uid}", get(get_task))
+        .with_state(meili.clone());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    (Client::new(url, Some("key")).unwrap(), meili)
+}
+
+fn vocabulary_bank() -> Bank {
+    let group = ["mac and cheese", "mac n cheese", "macaroni"];
+    let synonyms = group
+        .iter()
+        .map(|term| {
+            let terms = group
+                .iter()
+                .filter(|other| *other != term)
+                .map(|other| other.to_string())
+                .collect();
+            (term.to_string(), Synonyms { terms })
+        })
+        .collect();
+
+    Bank {
+        synonyms,
+        stop_words: vec!["the".to_string(), "with".to_string()],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn settings_carry_the_bank_vocabulary() {
+    let (client, meili) = fake_meilisearch().await;
+    let bank = vocabulary_bank();
+
+    update_settings(&client, FOOD_INDEX, TYPOS, &bank)
+        .await
+        .unwrap();
+
+    let meili = meili.lock().unwrap();
+    let [patch] = meili.patches.as_slice() else {
+        panic!("expected one settings update, got {:?}", meili.patches);
+    };
+    assert_eq!(
+        patch["synonyms"],
+        json!({
+            "mac and cheese": ["mac n cheese", "macaroni"],
+            "mac n cheese": ["mac and cheese", "macaroni"],
+            "macaroni": ["mac and cheese", "mac n cheese"],
+        })
+    );
+    assert_eq!(patch["stopWords"], json!(["the", "with"]));
+    assert_eq!(
+        patch["filterableAttributes"],
+        json!(["location", "meals", "tags", "served_today"])
+    );
+    assert_eq!(patch["sortableAttributes"], json!(["votes", "name"]));
+    assert_eq!(patch["distinctAttribute"], "name");
+    assert_eq!(
+        patch["typoTolerance"]["minWordSizeForTypos"],
+        json!({ "oneTypo": 4, "twoTypos": 8 })
+    );
+}
+
+#[tokio::test]
+async fn settings_are_updated_only_when_they_changed() {
+    let (client, meili) = fake_meilisearch().await;
+    let mut bank = vocabulary_bank();
+
+    // a live index never rebuilt gets its filters and sorts from the upsert path
+    assert!(
+        update_settings(&client, FOOD_INDEX, TYPOS, &bank)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !update_settings(&client, FOOD_INDEX, TYPOS, &bank)
+            .await
+            .unwrap()
+    );
+    assert_eq!(meili.lock().unwrap().patches.len(), 1);
+
+    // removed entries are sent empty, so they are cleared rather than left behind
+    bank.synonyms.clear();
+    bank.stop_words.pop();
+    assert!(
+        update_settings(&client, FOOD_INDEX, TYPOS, &bank)
+            .await
+            .unwrap()
+    );
+
+    let meili = meili.lock().unwrap();
+    let patch = meili.patches.last().unwrap();
+    assert_eq!(meili.patches.len(), 2);
+    assert_eq!(patch["synonyms"], json!({}));
+    assert_eq!(patch["stopWords"], json!(["the"]));
+}
+
+#[test]
+fn only_old_staging_indexes_are_stale() {
+    let now = 1_700_000_000_000;
+    let hour = 60 * 60 * 1000;
+
+    assert!(is_stale_staging(&format!("foods_{}", now - 2 * hour), now));
+    // another replica may still be filling it
+    assert!(!is_stale_staging(&format!("foods_{}", now - 1000), now));
+    assert!(!is_stale_staging("foods", now));
+    assert!(!is_stale_staging("foods_archive", now));
+}
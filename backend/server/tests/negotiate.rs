@@ -0,0 +1,102 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{
+        Request, StatusCode,
+        header::{ACCEPT, CONTENT_TYPE},
+    },
+};
+use bank::{
+    decode_payload,
+    payloads::{Error, VotesAccepted},
+};
+use common::TestServer;
+use serde_json::{Value, json};
+use server::negotiate::{JSON, PROTOBUF};
+
+fn json_vote(body: Value) -> Request<Body> {
+    Request::post("/votes")
+        .header(CONTENT_TYPE, JSON)
+        .header("cookie", "valid_id=frank")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn votes_accept_json() {
+    let server = TestServer::start().await;
+
+    let request = json_vote(json!({"old_bit_map": [0], "new_bit_map": [5], "next_food_id": 3}));
+    let (status, body) = server.send(request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        serde_json::from_slice::<Value>(&body).unwrap(),
+        json!({"votes": 2})
+    );
+
+    assert_eq!(server.votes(0).await, Some(1));
+    assert_eq!(server.votes(2).await, Some(1));
+    let bit_map = server.state.store().unwrap().user_votes("frank").await;
+    assert_eq!(bit_map.unwrap(), Some(vec![0b101]));
+}
+
+#[tokio::test]
+async fn votes_answer_protobuf_by_default() {
+    let server = TestServer::start().await;
+
+    let request = Request::post("/votes")
+        .body(Body::from(common::votes_body(&[0], &[1])))
+        .unwrap();
+    let (status, body) = server.send(request).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let accepted: VotesAccepted = decode_payload(body).unwrap();
+    assert_eq!(accepted.votes, 1);
+}
+
+#[tokio::test]
+async fn leaderboard_follows_accept() {
+    let server = TestServer::start().await;
+    server.vote(None, &[0], &[0b100]).await;
+
+    let request = Request::get("/leaderboard?location=Ford")
+        .header(ACCEPT, JSON)
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = server.send(request).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let leaderboard: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(leaderboard["entries"][0]["id"], 2);
+    assert_eq!(leaderboard["next_food_id"], 3);
+}
+
+#[tokio::test]
+async fn errors_carry_a_code_in_the_requested_format() {
+    let server = TestServer::start().await;
+
+    let (status, body) = server.send(json_vote(json!({"old_bit_map": "nope"}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let error: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(error["code"], "malformed_payload");
+
+    let request = Request::post("/votes")
+        .header(CONTENT_TYPE, PROTOBUF)
+        .body(Body::from(common::versioned_votes_body(9, &[0], &[1])))
+        .unwrap();
+    let (status, body) = server.send(request).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let error: Error = decode_payload(body).unwrap();
+    assert_eq!(error.code, "unknown_bank");
+}
+
+#[tokio::test]
+async fn errors_stay_plain_text_without_a_format() {
+    let server = TestServer::start().await;
+
+    let garbage = Request::post("/votes").body("garbage".into()).unwrap();
+    let (status, body) = server.send(garbage).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(&body[..], b"Malformed payload");
+}
@@ -0,0 +1,348 @@
+mod common;
+
+use std::{
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
+
+use axum::http::{Request, StatusCode, header::CONTENT_TYPE};
+use bank::payloads::SearchRequest;
+use common::{FakeBank, StubSearch, TestServer, test_bank};
+use serde_json::{Value, json};
+use server::{
+    config::Config,
+    error::AppError,
+    search::{FoodQuery, SEARCH_LIMIT, SearchBounds},
+    state::{Backends, State},
+};
+use tempfile::TempDir;
+use tokio::time::{sleep, timeout};
+
+fn json_search(body: Value) -> Request<axum::body::Body> {
+    Request::get("/search")
+        .header(CONTENT_TYPE, "application/json")
+        .body(body.to_string().into())
+        .unwrap()
+}
+
+async fn search(server: &TestServer, body: Value) -> Value {
+    let (status, body) = server.send(json_search(body)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn search_is_served_once_indexed() {
+    let server = TestServer::start().await;
+
+    // answered in the format it was asked in
+    let response = search(&server, json!({"token": "pickle"})).await;
+    assert_eq!(response["token"], "pickle");
+    assert_eq!(response["ids"], json!([]));
+}
+
+#[tokio::test]
+async fn search_counts_facets_over_every_match() {
+    let server = TestServer::start().await;
+
+    let response = search(&server, json!({"tags": ["Vegan"], "served_today": true})).await;
+    assert_eq!(response["ids"], json!([0, 2]));
+    assert_eq!(response["total"], 2);
+    assert_eq!(response["locations"], json!({"Ford": 2}));
+    assert_eq!(
+        response["meals"],
+        json!({"Breakfast": 1, "Lunch": 1, "Dinner": 1})
+    );
+    assert_eq!(response["tags"], json!({"Vegan": 2, "Spicy": 1}));
+
+    // Bread is not served today
+    let response = search(&server, json!({"tags": ["Vegan"]})).await;
+    assert_eq!(response["ids"], json!([0, 1, 2]));
+}
+
+#[tokio::test]
+async fn search_matches_any_meal_and_every_tag() {
+    let server = TestServer::start().await;
+
+    let response = search(&server, json!({"meals": ["Lunch", "Dinner"]})).await;
+    assert_eq!(response["ids"], json!([0, 2]));
+
+    let response = search(&server, json!({"tags": ["Vegan", "Spicy"]})).await;
+    assert_eq!(response["ids"], json!([2]));
+
+    let response = search(&server, json!({"token": "cur", "locations": ["Wiley"]})).await;
+    assert_eq!(response["ids"], json!([]));
+}
+
+#[tokio::test]
+async fn search_sorts_and_pages() {
+    let server = TestServer::start().await;
+
+    let response = search(&server, json!({"sort": "name:desc"})).await;
+    assert_eq!(response["ids"], json!([2, 1, 0]));
+
+    let response = search(
+        &server,
+        json!({"sort": "name:asc", "limit": 1, "offset": 1}),
+    )
+    .await;
+    assert_eq!(response["ids"], json!([1]));
+    assert_eq!(response["total"], 3);
+}
+
+#[tokio::test]
+async fn search_caps_the_limit() {
+    let server = TestServer::start_with("search_max_limit = 2").await;
+
+    let response = search(&server, json!({"limit": 50})).await;
+    assert_eq!(response["ids"], json!([0, 1]));
+    assert_eq!(response["total"], 3);
+}
+
+#[tokio::test]
+async fn search_highlights_are_offsets() {
+    let server = TestServer::start().await;
+
+    let response = search(&server, json!({"token": "rr"})).await;
+    assert_eq!(response["highlights"], json!([]));
+
+    let response = search(&server, json!({"token": "rr", "highlight": ["name"]})).await;
+    assert_eq!(response["ids"], json!([2]));
+    assert_eq!(
+        response["highlights"],
+        json!([{"id": 2, "attribute": "name", "start": 2, "length": 2}])
+    );
+}
+
+#[tokio::test]
+async fn search_rejects_parameters_off_the_allow_list() {
+    let server = TestServer::start().await;
+
+    for body in [
+        json!({"sort": "location:asc"}),
+        json!({"highlight": ["location"]}),
+        json!({"matching_strategy": "fuzzy"}),
+        json!({"offset": 5000}),
+    ] {
+        let (status, body) = server.send(json_search(body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let error: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["code"], "invalid_parameter");
+    }
+}
+
+const BOUNDS: SearchBounds = SearchBounds {
+    max_limit: 100,
+    max_offset: 1000,
+    typo_one_min: 5,
+};
+
+#[test]
+fn parse_fills_in_defaults() {
+    let query = FoodQuery::parse(SearchRequest::default(), BOUNDS).unwrap();
+    assert_eq!(query.limit, SEARCH_LIMIT);
+    assert_eq!(query.sort, None);
+    assert!(query.matching.is_none());
+
+    let request = SearchRequest {
+        sort: "votes:desc".to_string(),
+        limit: 500,
+        matching_strategy: "all".to_string(),
+        ..Default::default()
+    };
+    let query = FoodQuery::parse(request, BOUNDS).unwrap();
+    assert_eq!(query.limit, 100);
+    assert_eq!(query.sort, Some("votes:desc"));
+}
+
+#[test]
+fn parse_rejects_offsets_past_the_maximum() {
+    let request = |offset| SearchRequest {
+        offset,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        FoodQuery::parse(request(1000), BOUNDS).unwrap().offset,
+        1000
+    );
+    assert!(matches!(
+        FoodQuery::parse(request(1001), BOUNDS),
+        Err(AppError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn typo_min_only_tightens_the_index_setting() {
+    let query = |typo_min| {
+        let request = SearchRequest {
+            token: r#"pho "chicken" curry"#.to_string(),
+            typo_min,
+            ..Default::default()
+        };
+        FoodQuery::parse(request, BOUNDS).unwrap()
+    };
+
+    // at or below SEARCH_TYPO_ONE_MIN the index already decides
+    for typo_min in [0, 3, 5] {
+        assert_eq!(query(typo_min).typo_min, 0);
+        assert_eq!(query(typo_min).meili_text(), r#"pho "chicken" curry"#);
+    }
+
+    let strict = query(7);
+    assert_eq!(strict.typo_min, 7);
+    assert_eq!(strict.meili_text(), r#""pho" chicken "curry""#);
+}
+
+#[test]
+fn filters_are_quoted_for_meilisearch() {
+    let query = FoodQuery {
+        locations: vec!["Ford".to_string(), r#"Wi"ley\"#.to_string()],
+        tags: vec!["Vegan".to_string(), "Spicy".to_string()],
+        served_today: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        query.filter().unwrap(),
+        r#"location IN ["Ford", "Wi\"ley\\"] AND tags = "Vegan" AND tags = "Spicy" AND served_today = true"#
+    );
+    assert_eq!(FoodQuery::default().filter(), None);
+}
+
+#[tokio::test]
+async fn search_falls_back_to_memory_before_indexing() {
+    let server = TestServer::build(TempDir::new().unwrap(), "").await;
+
+    let response = search(&server, json!({"token": "cury", "highlight": ["name"]})).await;
+    assert_eq!(response["ids"], json!([2]));
+    assert_eq!(
+        response["highlights"],
+        json!([{"id": 2, "attribute": "name", "start": 0, "length": 5}])
+    );
+    assert_eq!(response["tags"], json!({"Spicy": 1, "Vegan": 1}));
+}
+
+#[tokio::test]
+async fn search_falls_back_to_memory_when_meilisearch_fails() {
+    let server = TestServer::start().await;
+    server.search.failing.store(true, Ordering::Release);
+
+    let response = search(&server, json!({"token": "app", "locations": ["Ford"]})).await;
+    assert_eq!(response["ids"], json!([0]));
+    assert_eq!(response["total"], 1);
+}
+
+#[tokio::test]
+async fn startup_rebuilds_the_index() {
+    let server = TestServer::start().await;
+
+    assert_eq!(server.search.rebuilds(), 1);
+    assert_eq!(server.search.votes(0), Some(0));
+    assert_eq!(server.search.votes(2), Some(0));
+}
+
+#[tokio::test]
+async fn startup_upserts_without_rebuild() {
+    let server = TestServer::start_with("rebuild_on_startup = false").await;
+
+    assert_eq!(server.search.rebuilds(), 0);
+    assert_eq!(server.search.votes(1), Some(0));
+}
+
+#[tokio::test]
+async fn readiness_follows_the_backends() {
+    let server = TestServer::build(TempDir::new().unwrap(), "").await;
+    assert_eq!(
+        server.get("/readyz").await.0,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    server::state::connect_backends(server.state.clone()).await;
+    assert_eq!(server.get("/readyz").await.0, StatusCode::OK);
+}
+
+async fn get_readiness(server: &TestServer) -> (StatusCode, Value) {
+    let (status, body) = server.get("/readyz").await;
+
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+// polls /readyz until `done` holds, the backoff starts at 500ms
+async fn wait_for_readiness(server: &TestServer, done: impl Fn(&Value) -> bool) -> Value {
+    timeout(Duration::from_secs(5), async {
+        loop {
+            let (_, readiness) = get_readiness(server).await;
+            if done(&readiness) {
+                return readiness;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn backends_keep_reconnecting_past_the_startup_retries() {
+    let server = TestServer::build(TempDir::new().unwrap(), "").await;
+    server.search.down.store(true, Ordering::Release);
+    tokio::spawn(server::state::connect_backends(server.state.clone()));
+
+    let readiness = wait_for_readiness(&server, |readiness| {
+        readiness["search"]["error"] == "Internal error: stub search is down"
+    })
+    .await;
+    assert_eq!(readiness["store"]["ok"], true);
+
+    server.search.down.store(false, Ordering::Release);
+    wait_for_readiness(&server, |readiness| readiness["search"]["ok"] == true).await;
+    assert_eq!(get_readiness(&server).await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn readiness_reports_the_missing_store() {
+    let snapshots = TempDir::new().unwrap();
+    // an unreadable snapshot fails every attempt to open the memory store
+    std::fs::write(snapshots.path().join("snapshot_1.bin"), b"garbage").unwrap();
+    let server = TestServer::build(snapshots, "").await;
+    tokio::spawn(server::state::connect_backends(server.state.clone()));
+
+    let readiness = wait_for_readiness(&server, |readiness| {
+        readiness["store"]["error"] != "Not connected yet"
+    })
+    .await;
+    assert_eq!(readiness["store"]["ok"], false);
+    assert_eq!(readiness["search"]["error"], "Waiting for Memory");
+
+    std::fs::remove_file(server.snapshots.path().join("snapshot_1.bin")).unwrap();
+    wait_for_readiness(&server, |readiness| {
+        readiness["store"]["ok"] == true && readiness["search"]["ok"] == true
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn an_unavailable_bank_is_returned_not_panicked() {
+    // an alias past next_food_id fails to index like a fetch that errors
+    let mut bank = test_bank();
+    bank.food_aliases.insert(9, 0);
+    let config = Config::from_toml(
+        "meili_key = \"test\"\n\
+         vote_store = \"memory\"\n\
+         verify_token = false\n\
+         startup_retries = 1",
+    )
+    .unwrap();
+
+    let state = State::with_backends(
+        config,
+        Backends {
+            bank_source: Arc::new(FakeBank::new(bank)),
+            search: Arc::new(StubSearch::default()),
+        },
+    )
+    .await;
+    assert!(state.is_err());
+}
@@ -0,0 +1,62 @@
+//! Rules every vote store shares, Redis only runs with `TEST_REDIS_URL` set.
+//! Its database is flushed, point it at a throwaway instance.
+use std::{env, sync::Arc};
+
+use server::{
+    database::Vote,
+    error::AppError,
+    memory::MemoryStore,
+    store::{RedisStore, VoteStore},
+};
+
+const TEST_REDIS_URL: &str = "TEST_REDIS_URL";
+
+async fn stores() -> Vec<(&'static str, Arc<dyn VoteStore>)> {
+    let mut stores: Vec<(&'static str, Arc<dyn VoteStore>)> =
+        vec![("memory", Arc::new(MemoryStore::new()))];
+
+    if let Ok(url) = env::var(TEST_REDIS_URL) {
+        let client = redis::Client::open(url.as_str()).unwrap();
+        let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+        redis::cmd("FLUSHDB")
+            .query_async::<()>(&mut connection)
+            .await
+            .unwrap();
+
+        stores.push(("redis", Arc::new(RedisStore::connect(&url).await.unwrap())));
+    }
+
+    stores
+}
+
+#[tokio::test]
+async fn unknown_foods_reject_the_whole_batch() {
+    for (name, store) in stores().await {
+        store
+            .populate(&["Apple".to_string(), String::new()])
+            .await
+            .unwrap();
+
+        // id 1 is a hole in the bank, id 5 past its end
+        for unknown in [1, 5] {
+            let result = store
+                .update_foods(&[(0, Vote::Increment), (unknown, Vote::Increment)])
+                .await;
+            assert!(
+                matches!(result, Err(AppError::NotFound(_))),
+                "{name}: {result:?}"
+            );
+            assert_eq!(store.votes(0).await.unwrap(), Some(0), "{name}");
+        }
+
+        store.update_foods(&[(0, Vote::Increment)]).await.unwrap();
+        assert_eq!(store.votes(0).await.unwrap(), Some(1), "{name}");
+
+        // known foods in a batch still never drop below 0
+        store
+            .update_foods(&[(0, Vote::Decrement), (0, Vote::Decrement)])
+            .await
+            .unwrap();
+        assert_eq!(store.votes(0).await.unwrap(), Some(0), "{name}");
+    }
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header::SET_COOKIE},
+    response::Response,
+};
+use common::TestServer;
+use hmac::{Hmac, Mac};
+use server::{
+    router,
+    token::{sign, verify},
+    utils::now_ms,
+};
+use sha2::Sha256;
+use tower::ServiceExt;
+
+const KEY: &str = "secret";
+const EXPIRY_MS: u64 = 300_000;
+const REFRESH_MS: u64 = 240_000;
+
+async fn token_server() -> TestServer {
+    TestServer::start_with(&format!(
+        "verify_token = true\n\
+         token_key = \"{KEY}\"\n\
+         token_expiry_ms = {EXPIRY_MS}\n\
+         token_debounce_ms = 200\n\
+         token_refresh_ms = {REFRESH_MS}"
+    ))
+    .await
+}
+
+// minted like +server.ts: `${Date.now()}.${createHmac("sha256", JWT_KEY).update(timestamp).digest("hex")}`
+fn frontend_token(age_ms: u64) -> String {
+    let timestamp = (now_ms() - age_ms).to_string();
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(KEY.as_bytes()).unwrap();
+    mac.update(timestamp.as_bytes());
+
+    format!("{timestamp}.{}", hex::encode(mac.finalize().into_bytes()))
+}
+
+async fn get_bank(server: &TestServer, token: Option<&str>, refresh: bool) -> Response {
+    let mut request = Request::get("/bank");
+    if let Some(token) = token {
+        request = request.header("cookie", format!("token={token}"));
+    }
+    if refresh {
+        request = request.header("x-refresh", "1");
+    }
+
+    router(server.state.clone())
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+// the issue time of the token the response set, None without a cookie
+fn issued(response: &Response) -> Option<u64> {
+    let cookie = response.headers().get(SET_COOKIE)?.to_str().unwrap();
+    let value = cookie.split(';').next()?.strip_prefix("token=")?;
+
+    verify(KEY, value)
+}
+
+#[test]
+fn tokens_round_trip() {
+    let issued_ms = 1_700_000_000_000;
+
+    assert_eq!(verify(KEY, &sign(KEY, issued_ms)), Some(issued_ms));
+    assert_eq!(verify("other", &sign(KEY, issued_ms)), None);
+}
+
+#[test]
+fn frontend_tokens_verify() {
+    let token = frontend_token(0);
+    let (timestamp, _) = token.split_once('.').unwrap();
+
+    assert_eq!(verify(KEY, &token), timestamp.parse().ok());
+    assert_eq!(sign(KEY, timestamp.parse().unwrap()), token);
+}
+
+#[tokio::test]
+async fn missing_and_tampered_tokens_are_rejected() {
+    let server = token_server().await;
+
+    let response = get_bank(&server, None, false).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // flipped signature byte
+    let mut tampered = frontend_token(1000);
+    let last = tampered.pop().unwrap();
+    tampered.push(if last == '0' { '1' } else { '0' });
+    let response = get_bank(&server, Some(&tampered), false).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // a claim added to a signed timestamp
+    let plain = frontend_token(1000);
+    let (timestamp, signature) = plain.split_once('.').unwrap();
+    let forged = format!("{timestamp}.r.{signature}");
+    let response = get_bank(&server, Some(&forged), false).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn expired_tokens_are_rejected() {
+    let server = token_server().await;
+
+    let response = get_bank(&server, Some(&frontend_token(EXPIRY_MS + 1000)), false).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = get_bank(&server, Some(&frontend_token(EXPIRY_MS - 1000)), false).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn requests_inside_the_debounce_are_rejected() {
+    let server = token_server().await;
+
+    let response = get_bank(&server, Some(&frontend_token(0)), false).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // the header does not skip it either
+    let response = get_bank(&server, Some(&frontend_token(0)), true).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn fresh_tokens_keep_their_cookie() {
+    let server = token_server().await;
+
+    let response = get_bank(&server, Some(&frontend_token(1000)), false).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(SET_COOKIE).is_none());
+}
+
+#[tokio::test]
+async fn refresh_header_reissues_the_token() {
+    let server = token_server().await;
+
+    let before = now_ms();
+    let response = get_bank(&server, Some(&frontend_token(1000)), true).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(issued(&response).unwrap() >= before);
+}
+
+#[tokio::test]
+async fn stale_tokens_are_reissued() {
+    let server = token_server().await;
+
+    let before = now_ms();
+    let response = get_bank(&server, Some(&frontend_token(REFRESH_MS + 1000)), false).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(issued(&response).unwrap() >= before);
+}
@@ -0,0 +1,270 @@
+mod common;
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode, header::RETRY_AFTER},
+};
+use bank::{RemoteBank, decode_payload, payloads::Leaderboard};
+use common::{TestServer, test_bank, versioned_votes_body, votes_body};
+use server::{error::BANK_VERSION_HEADER, router};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn votes_apply_flipped_bits() {
+    let server = TestServer::start().await;
+
+    assert_eq!(server.vote(None, &[0b000], &[0b101]).await, StatusCode::OK);
+    assert_eq!(server.votes(0).await, Some(1));
+    assert_eq!(server.votes(1).await, Some(0));
+    assert_eq!(server.votes(2).await, Some(1));
+
+    assert_eq!(server.vote(None, &[0b101], &[0b011]).await, StatusCode::OK);
+    assert_eq!(server.votes(0).await, Some(1));
+    assert_eq!(server.votes(1).await, Some(1));
+    assert_eq!(server.votes(2).await, Some(0));
+}
+
+#[tokio::test]
+async fn votes_never_drop_below_zero() {
+    let server = TestServer::start().await;
+
+    assert_eq!(server.vote(None, &[0b001], &[0b000]).await, StatusCode::OK);
+    assert_eq!(server.votes(0).await, Some(0));
+}
+
+#[tokio::test]
+async fn votes_store_the_user_bitmap() {
+    let server = TestServer::start().await;
+
+    server.vote(Some("alice"), &[0b000], &[0b110]).await;
+
+    let bit_map = server.state.store().unwrap().user_votes("alice").await;
+    assert_eq!(bit_map.unwrap(), Some(vec![0b110]));
+}
+
+#[tokio::test]
+async fn votes_reject_malformed_payloads() {
+    let server = TestServer::start().await;
+
+    let garbage = Request::post("/votes").body("garbage".into()).unwrap();
+    assert_eq!(server.send(garbage).await.0, StatusCode::BAD_REQUEST);
+
+    // lengths differ
+    assert_eq!(
+        server.vote(None, &[0b000], &[0b001, 0b000]).await,
+        StatusCode::BAD_REQUEST
+    );
+
+    // 3 foods fit in 1 byte
+    assert_eq!(
+        server.vote(None, &[0; 2], &[0, 1]).await,
+        StatusCode::BAD_REQUEST
+    );
+}
+
+fn request_from(peer: &str, forwarded: &str, user: &str) -> Request<Body> {
+    let peer: SocketAddr = format!("{peer}:40000").parse().unwrap();
+    let mut request = Request::post("/votes")
+        .header("x-forwarded-for", forwarded)
+        .header("cookie", format!("valid_id={user}"))
+        .body(Body::from(votes_body(&[0], &[1])))
+        .unwrap();
+    request.extensions_mut().insert(ConnectInfo(peer));
+
+    request
+}
+
+// a vote from the socket peer `peer` carrying `forwarded` as X-Forwarded-For
+async fn vote_from(server: &TestServer, peer: &str, forwarded: &str) -> StatusCode {
+    server.send(request_from(peer, forwarded, "bob")).await.0
+}
+
+#[tokio::test]
+async fn rate_limited_votes_say_when_to_retry() {
+    let server = TestServer::start_with(
+        "ip_rate_burst = 1
+ip_rate_refill_ms = 60000",
+    )
+    .await;
+
+    let response = router(server.state.clone())
+        .oneshot(request_from("203.0.113.5", "", "bob"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router(server.state.clone())
+        .oneshot(request_from("203.0.113.5", "", "bob"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()[RETRY_AFTER], "60");
+}
+
+#[tokio::test]
+async fn rotating_the_user_cookie_does_not_refill_the_bucket() {
+    let server = TestServer::start_with(
+        "ip_rate_burst = 1
+ip_rate_refill_ms = 60000",
+    )
+    .await;
+
+    assert_eq!(
+        server.send(request_from("203.0.113.5", "", "bob")).await.0,
+        StatusCode::OK
+    );
+    assert_eq!(
+        server
+            .send(request_from("203.0.113.5", "", "mallory"))
+            .await
+            .0,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
+
+#[tokio::test]
+async fn votes_above_the_flip_cap_say_when_to_retry() {
+    let server = TestServer::start_with(
+        "max_vote_flips = 1
+ip_rate_refill_ms = 2500",
+    )
+    .await;
+
+    let request = Request::post("/votes")
+        .body(Body::from(votes_body(&[0b000], &[0b011])))
+        .unwrap();
+    let response = router(server.state.clone()).oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()[RETRY_AFTER], "3");
+    assert_eq!(server.votes(0).await, Some(0));
+}
+
+#[tokio::test]
+async fn spoofed_forwarded_for_does_not_change_the_bucket() {
+    let server = TestServer::start_with(
+        "ip_rate_burst = 1\nip_rate_refill_ms = 60000\ntrusted_proxies = \"10.0.0.0/8\"",
+    )
+    .await;
+
+    // a direct client is limited by its socket address whatever it claims
+    assert_eq!(
+        vote_from(&server, "203.0.113.5", "198.51.100.1").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        vote_from(&server, "203.0.113.5", "198.51.100.2").await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // behind the proxy the hop it appended counts, not the one the client prepended
+    assert_eq!(
+        vote_from(&server, "10.0.0.1", "198.51.100.3, 198.51.100.7").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        vote_from(&server, "10.0.0.1", "198.51.100.4, 198.51.100.7").await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // another client through the same proxy keeps its own bucket
+    assert_eq!(
+        vote_from(&server, "10.0.0.1", "198.51.100.8").await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn votes_are_unavailable_before_the_store_connects() {
+    let server = TestServer::build(tempfile::TempDir::new().unwrap(), "").await;
+
+    assert_eq!(
+        server.vote(None, &[0], &[1]).await,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+}
+
+#[tokio::test]
+async fn leaderboard_orders_by_votes() {
+    let server = TestServer::start().await;
+
+    server.vote(Some("a"), &[0b000], &[0b110]).await;
+    server.vote(Some("b"), &[0b000], &[0b100]).await;
+
+    let (status, body) = server.get("/leaderboard?location=Ford").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let leaderboard: Leaderboard = decode_payload(body).unwrap();
+    let entries: Vec<(u32, i64)> = leaderboard
+        .entries
+        .iter()
+        .map(|entry| (entry.id, entry.votes))
+        .collect();
+    assert_eq!(entries, vec![(2, 2), (0, 0)]);
+    assert_eq!(leaderboard.next_food_id, 3);
+}
+
+#[tokio::test]
+async fn votes_from_an_older_bank_are_padded() {
+    let server = TestServer::start().await;
+
+    // built against a bank of 2 foods, bit 2 is padding
+    let body = versioned_votes_body(2, &[0b000], &[0b111]);
+    let request = Request::post("/votes")
+        .header("cookie", "valid_id=erin")
+        .body(Body::from(body))
+        .unwrap();
+    assert_eq!(server.send(request).await.0, StatusCode::OK);
+
+    assert_eq!(server.votes(0).await, Some(1));
+    assert_eq!(server.votes(1).await, Some(1));
+    assert_eq!(server.votes(2).await, Some(0));
+
+    let bit_map = server.state.store().unwrap().user_votes("erin").await;
+    assert_eq!(bit_map.unwrap(), Some(vec![0b011]));
+}
+
+#[tokio::test]
+async fn votes_from_a_newer_bank_ask_for_a_refetch() {
+    let server = TestServer::start().await;
+
+    let request = Request::post("/votes")
+        .body(Body::from(versioned_votes_body(9, &[0, 0], &[0, 1])))
+        .unwrap();
+    let response = router(server.state.clone()).oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(response.headers()[BANK_VERSION_HEADER], "3");
+    assert_eq!(server.votes(0).await, Some(0));
+}
+
+#[tokio::test]
+async fn votes_on_an_alias_bit_count_for_the_kept_food() {
+    let server = TestServer::start().await;
+
+    // id 3 collided with Curry 2 when the keys were sanitized
+    let mut bank = test_bank();
+    bank.next_food_id = 4;
+    bank.food_aliases.insert(3, 2);
+    server
+        .state
+        .remote_bank
+        .store(Arc::new(RemoteBank::new(bank).unwrap()));
+
+    server.vote(Some("frank"), &[0b0000], &[0b1000]).await;
+    assert_eq!(server.votes(2).await, Some(1));
+    assert_eq!(server.votes(3).await, None);
+
+    let bit_map = server.state.store().unwrap().user_votes("frank").await;
+    assert_eq!(bit_map.unwrap(), Some(vec![0b0100]));
+
+    // the alias and the kept bit together are still one vote
+    server.vote(Some("grace"), &[0b0000], &[0b1100]).await;
+    assert_eq!(server.votes(2).await, Some(2));
+
+    server.vote(Some("grace"), &[0b1100], &[0b1000]).await;
+    assert_eq!(server.votes(2).await, Some(2));
+}
@@ -0,0 +1,316 @@
+// Generated by `cargo codegen` from foods.proto, do not edit.
+
+import { Reader, WireType, Writer } from "./protobuf";
+
+export interface Food {
+  id: number;
+  location: string;
+  meals: string[];
+  tags: string[];
+}
+
+export const Food = {
+  create(fields: Partial<Food> = {}): Food {
+    return {
+      id: 0,
+      location: "",
+      meals: [],
+      tags: [],
+      ...fields,
+    };
+  },
+
+  encode(message: Food, writer: Writer = new Writer()): Writer {
+    if (message.id !== 0) {
+      writer.tag(1, WireType.Varint).uint32(message.id);
+    }
+    if (message.location !== "") {
+      writer.tag(2, WireType.LengthDelimited).string(message.location);
+    }
+    for (const value of message.meals) {
+      writer.tag(3, WireType.LengthDelimited).string(value);
+    }
+    for (const value of message.tags) {
+      writer.tag(4, WireType.LengthDelimited).string(value);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): Food {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = Food.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.id = reader.uint32();
+          break;
+        }
+        case 2: {
+          message.location = reader.string();
+          break;
+        }
+        case 3: {
+          message.meals.push(reader.string());
+          break;
+        }
+        case 4: {
+          message.tags.push(reader.string());
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface Synonyms {
+  terms: string[];
+}
+
+export const Synonyms = {
+  create(fields: Partial<Synonyms> = {}): Synonyms {
+    return {
+      terms: [],
+      ...fields,
+    };
+  },
+
+  encode(message: Synonyms, writer: Writer = new Writer()): Writer {
+    for (const value of message.terms) {
+      writer.tag(1, WireType.LengthDelimited).string(value);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): Synonyms {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = Synonyms.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.terms.push(reader.string());
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface Bank {
+  next_food_id: number;
+  next_location_id: number;
+  foods: { [key: string]: Food };
+  locations: { [key: string]: number };
+  food_aliases: { [key: number]: number };
+  location_aliases: { [key: number]: number };
+  served_on: string;
+  synonyms: { [key: string]: Synonyms };
+  stop_words: string[];
+}
+
+export const Bank = {
+  create(fields: Partial<Bank> = {}): Bank {
+    return {
+      next_food_id: 0,
+      next_location_id: 0,
+      foods: {},
+      locations: {},
+      food_aliases: {},
+      location_aliases: {},
+      served_on: "",
+      synonyms: {},
+      stop_words: [],
+      ...fields,
+    };
+  },
+
+  encode(message: Bank, writer: Writer = new Writer()): Writer {
+    if (message.next_food_id !== 0) {
+      writer.tag(1, WireType.Varint).uint32(message.next_food_id);
+    }
+    if (message.next_location_id !== 0) {
+      writer.tag(2, WireType.Varint).uint32(message.next_location_id);
+    }
+    for (const [key, value] of Object.entries(message.foods)) {
+      writer.tag(3, WireType.LengthDelimited).fork();
+      if (key !== "") {
+        writer.tag(1, WireType.LengthDelimited).string(key);
+      }
+      if (value != null) {
+        Food.encode(value, writer.tag(2, WireType.LengthDelimited).fork()).ldelim();
+      }
+      writer.ldelim();
+    }
+    for (const [key, value] of Object.entries(message.locations)) {
+      writer.tag(4, WireType.LengthDelimited).fork();
+      if (key !== "") {
+        writer.tag(1, WireType.LengthDelimited).string(key);
+      }
+      if (value !== 0) {
+        writer.tag(2, WireType.Varint).uint32(value);
+      }
+      writer.ldelim();
+    }
+    for (const [key, value] of Object.entries(message.food_aliases)) {
+      writer.tag(5, WireType.LengthDelimited).fork();
+      if (Number(key) !== 0) {
+        writer.tag(1, WireType.Varint).uint32(Number(key));
+      }
+      if (value !== 0) {
+        writer.tag(2, WireType.Varint).uint32(value);
+      }
+      writer.ldelim();
+    }
+    for (const [key, value] of Object.entries(message.location_aliases)) {
+      writer.tag(6, WireType.LengthDelimited).fork();
+      if (Number(key) !== 0) {
+        writer.tag(1, WireType.Varint).uint32(Number(key));
+      }
+      if (value !== 0) {
+        writer.tag(2, WireType.Varint).uint32(value);
+      }
+      writer.ldelim();
+    }
+    if (message.served_on !== "") {
+      writer.tag(7, WireType.LengthDelimited).string(message.served_on);
+    }
+    for (const [key, value] of Object.entries(message.synonyms)) {
+      writer.tag(8, WireType.LengthDelimited).fork();
+      if (key !== "") {
+        writer.tag(1, WireType.LengthDelimited).string(key);
+      }
+      if (value != null) {
+        Synonyms.encode(value, writer.tag(2, WireType.LengthDelimited).fork()).ldelim();
+      }
+      writer.ldelim();
+    }
+    for (const value of message.stop_words) {
+      writer.tag(9, WireType.LengthDelimited).string(value);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): Bank {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = Bank.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.next_food_id = reader.uint32();
+          break;
+        }
+        case 2: {
+          message.next_location_id = reader.uint32();
+          break;
+        }
+        case 3: {
+          const entry = reader.end(reader.uint32());
+          let key = "";
+          let value = Food.create();
+          while (reader.pos < entry) {
+            const [entryField, entryWireType] = reader.tag();
+            if (entryField === 1) {
+              key = reader.string();
+            } else if (entryField === 2) {
+              value = Food.decode(reader, reader.uint32());
+            } else {
+              reader.skip(entryWireType);
+            }
+          }
+          message.foods[key] = value;
+          break;
+        }
+        case 4: {
+          const entry = reader.end(reader.uint32());
+          let key = "";
+          let value = 0;
+          while (reader.pos < entry) {
+            const [entryField, entryWireType] = reader.tag();
+            if (entryField === 1) {
+              key = reader.string();
+            } else if (entryField === 2) {
+              value = reader.uint32();
+            } else {
+              reader.skip(entryWireType);
+            }
+          }
+          message.locations[key] = value;
+          break;
+        }
+        case 5: {
+          const entry = reader.end(reader.uint32());
+          let key = 0;
+          let value = 0;
+          while (reader.pos < entry) {
+            const [entryField, entryWireType] = reader.tag();
+            if (entryField === 1) {
+              key = reader.uint32();
+            } else if (entryField === 2) {
+              value = reader.uint32();
+            } else {
+              reader.skip(entryWireType);
+            }
+          }
+          message.food_aliases[key] = value;
+          break;
+        }
+        case 6: {
+          const entry = reader.end(reader.uint32());
+          let key = 0;
+          let value = 0;
+          while (reader.pos < entry) {
+            const [entryField, entryWireType] = reader.tag();
+            if (entryField === 1) {
+              key = reader.uint32();
+            } else if (entryField === 2) {
+              value = reader.uint32();
+            } else {
+              reader.skip(entryWireType);
+            }
+          }
+          message.location_aliases[key] = value;
+          break;
+        }
+        case 7: {
+          message.served_on = reader.string();
+          break;
+        }
+        case 8: {
+          const entry = reader.end(reader.uint32());
+          let key = "";
+          let value = Synonyms.create();
+          while (reader.pos < entry) {
+            const [entryField, entryWireType] = reader.tag();
+            if (entryField === 1) {
+              key = reader.string();
+            } else if (entryField === 2) {
+              value = Synonyms.decode(reader, reader.uint32());
+            } else {
+              reader.skip(entryWireType);
+            }
+          }
+          message.synonyms[key] = value;
+          break;
+        }
+        case 9: {
+          message.stop_words.push(reader.string());
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
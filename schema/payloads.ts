@@ -0,0 +1,762 @@
+// Generated by `cargo codegen` from payloads.proto, do not edit.
+
+import { Reader, WireType, Writer } from "./protobuf";
+
+export interface Votes {
+  old_bit_map: Uint8Array;
+  new_bit_map: Uint8Array;
+  next_food_id: number;
+}
+
+export const Votes = {
+  create(fields: Partial<Votes> = {}): Votes {
+    return {
+      old_bit_map: new Uint8Array(),
+      new_bit_map: new Uint8Array(),
+      next_food_id: 0,
+      ...fields,
+    };
+  },
+
+  encode(message: Votes, writer: Writer = new Writer()): Writer {
+    if (message.old_bit_map.length !== 0) {
+      writer.tag(1, WireType.LengthDelimited).bytes(message.old_bit_map);
+    }
+    if (message.new_bit_map.length !== 0) {
+      writer.tag(2, WireType.LengthDelimited).bytes(message.new_bit_map);
+    }
+    if (message.next_food_id !== 0) {
+      writer.tag(3, WireType.Varint).uint32(message.next_food_id);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): Votes {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = Votes.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.old_bit_map = reader.bytes();
+          break;
+        }
+        case 2: {
+          message.new_bit_map = reader.bytes();
+          break;
+        }
+        case 3: {
+          message.next_food_id = reader.uint32();
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface LeaderboardEntry {
+  id: number;
+  votes: number;
+}
+
+export const LeaderboardEntry = {
+  create(fields: Partial<LeaderboardEntry> = {}): LeaderboardEntry {
+    return {
+      id: 0,
+      votes: 0,
+      ...fields,
+    };
+  },
+
+  encode(message: LeaderboardEntry, writer: Writer = new Writer()): Writer {
+    if (message.id !== 0) {
+      writer.tag(1, WireType.Varint).uint32(message.id);
+    }
+    if (message.votes !== 0) {
+      writer.tag(2, WireType.Varint).int64(message.votes);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): LeaderboardEntry {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = LeaderboardEntry.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.id = reader.uint32();
+          break;
+        }
+        case 2: {
+          message.votes = reader.int64();
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface Leaderboard {
+  entries: LeaderboardEntry[];
+  next_food_id: number;
+}
+
+export const Leaderboard = {
+  create(fields: Partial<Leaderboard> = {}): Leaderboard {
+    return {
+      entries: [],
+      next_food_id: 0,
+      ...fields,
+    };
+  },
+
+  encode(message: Leaderboard, writer: Writer = new Writer()): Writer {
+    for (const value of message.entries) {
+      LeaderboardEntry.encode(value, writer.tag(1, WireType.LengthDelimited).fork()).ldelim();
+    }
+    if (message.next_food_id !== 0) {
+      writer.tag(2, WireType.Varint).uint32(message.next_food_id);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): Leaderboard {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = Leaderboard.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.entries.push(LeaderboardEntry.decode(reader, reader.uint32()));
+          break;
+        }
+        case 2: {
+          message.next_food_id = reader.uint32();
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface FoodVotes {
+  id: number;
+  votes: number;
+}
+
+export const FoodVotes = {
+  create(fields: Partial<FoodVotes> = {}): FoodVotes {
+    return {
+      id: 0,
+      votes: 0,
+      ...fields,
+    };
+  },
+
+  encode(message: FoodVotes, writer: Writer = new Writer()): Writer {
+    if (message.id !== 0) {
+      writer.tag(1, WireType.Varint).uint32(message.id);
+    }
+    if (message.votes !== 0) {
+      writer.tag(2, WireType.Varint).int64(message.votes);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): FoodVotes {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = FoodVotes.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.id = reader.uint32();
+          break;
+        }
+        case 2: {
+          message.votes = reader.int64();
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface UserVotes {
+  user: string;
+  bit_map: Uint8Array;
+}
+
+export const UserVotes = {
+  create(fields: Partial<UserVotes> = {}): UserVotes {
+    return {
+      user: "",
+      bit_map: new Uint8Array(),
+      ...fields,
+    };
+  },
+
+  encode(message: UserVotes, writer: Writer = new Writer()): Writer {
+    if (message.user !== "") {
+      writer.tag(1, WireType.LengthDelimited).string(message.user);
+    }
+    if (message.bit_map.length !== 0) {
+      writer.tag(2, WireType.LengthDelimited).bytes(message.bit_map);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): UserVotes {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = UserVotes.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.user = reader.string();
+          break;
+        }
+        case 2: {
+          message.bit_map = reader.bytes();
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface Snapshot {
+  version: number;
+  created_at_ms: number;
+  next_food_id: number;
+  foods: FoodVotes[];
+  users: UserVotes[];
+}
+
+export const Snapshot = {
+  create(fields: Partial<Snapshot> = {}): Snapshot {
+    return {
+      version: 0,
+      created_at_ms: 0,
+      next_food_id: 0,
+      foods: [],
+      users: [],
+      ...fields,
+    };
+  },
+
+  encode(message: Snapshot, writer: Writer = new Writer()): Writer {
+    if (message.version !== 0) {
+      writer.tag(1, WireType.Varint).uint32(message.version);
+    }
+    if (message.created_at_ms !== 0) {
+      writer.tag(2, WireType.Varint).uint64(message.created_at_ms);
+    }
+    if (message.next_food_id !== 0) {
+      writer.tag(3, WireType.Varint).uint32(message.next_food_id);
+    }
+    for (const value of message.foods) {
+      FoodVotes.encode(value, writer.tag(4, WireType.LengthDelimited).fork()).ldelim();
+    }
+    for (const value of message.users) {
+      UserVotes.encode(value, writer.tag(5, WireType.LengthDelimited).fork()).ldelim();
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): Snapshot {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = Snapshot.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.version = reader.uint32();
+          break;
+        }
+        case 2: {
+          message.created_at_ms = reader.uint64();
+          break;
+        }
+        case 3: {
+          message.next_food_id = reader.uint32();
+          break;
+        }
+        case 4: {
+          message.foods.push(FoodVotes.decode(reader, reader.uint32()));
+          break;
+        }
+        case 5: {
+          message.users.push(UserVotes.decode(reader, reader.uint32()));
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface VotesAccepted {
+  votes: number;
+}
+
+export const VotesAccepted = {
+  create(fields: Partial<VotesAccepted> = {}): VotesAccepted {
+    return {
+      votes: 0,
+      ...fields,
+    };
+  },
+
+  encode(message: VotesAccepted, writer: Writer = new Writer()): Writer {
+    if (message.votes !== 0) {
+      writer.tag(1, WireType.Varint).uint32(message.votes);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): VotesAccepted {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = VotesAccepted.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.votes = reader.uint32();
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface SearchRequest {
+  token: string;
+  locations: string[];
+  meals: string[];
+  tags: string[];
+  served_today: boolean;
+  sort: string;
+  limit: number;
+  offset: number;
+  highlight: string[];
+  matching_strategy: string;
+  typo_min: number;
+}
+
+export const SearchRequest = {
+  create(fields: Partial<SearchRequest> = {}): SearchRequest {
+    return {
+      token: "",
+      locations: [],
+      meals: [],
+      tags: [],
+      served_today: false,
+      sort: "",
+      limit: 0,
+      offset: 0,
+      highlight: [],
+      matching_strategy: "",
+      typo_min: 0,
+      ...fields,
+    };
+  },
+
+  encode(message: SearchRequest, writer: Writer = new Writer()): Writer {
+    if (message.token !== "") {
+      writer.tag(1, WireType.LengthDelimited).string(message.token);
+    }
+    for (const value of message.locations) {
+      writer.tag(2, WireType.LengthDelimited).string(value);
+    }
+    for (const value of message.meals) {
+      writer.tag(3, WireType.LengthDelimited).string(value);
+    }
+    for (const value of message.tags) {
+      writer.tag(4, WireType.LengthDelimited).string(value);
+    }
+    if (message.served_today) {
+      writer.tag(5, WireType.Varint).bool(message.served_today);
+    }
+    if (message.sort !== "") {
+      writer.tag(6, WireType.LengthDelimited).string(message.sort);
+    }
+    if (message.limit !== 0) {
+      writer.tag(7, WireType.Varint).uint32(message.limit);
+    }
+    if (message.offset !== 0) {
+      writer.tag(8, WireType.Varint).uint32(message.offset);
+    }
+    for (const value of message.highlight) {
+      writer.tag(9, WireType.LengthDelimited).string(value);
+    }
+    if (message.matching_strategy !== "") {
+      writer.tag(10, WireType.LengthDelimited).string(message.matching_strategy);
+    }
+    if (message.typo_min !== 0) {
+      writer.tag(11, WireType.Varint).uint32(message.typo_min);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): SearchRequest {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = SearchRequest.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.token = reader.string();
+          break;
+        }
+        case 2: {
+          message.locations.push(reader.string());
+          break;
+        }
+        case 3: {
+          message.meals.push(reader.string());
+          break;
+        }
+        case 4: {
+          message.tags.push(reader.string());
+          break;
+        }
+        case 5: {
+          message.served_today = reader.bool();
+          break;
+        }
+        case 6: {
+          message.sort = reader.string();
+          break;
+        }
+        case 7: {
+          message.limit = reader.uint32();
+          break;
+        }
+        case 8: {
+          message.offset = reader.uint32();
+          break;
+        }
+        case 9: {
+          message.highlight.push(reader.string());
+          break;
+        }
+        case 10: {
+          message.matching_strategy = reader.string();
+          break;
+        }
+        case 11: {
+          message.typo_min = reader.uint32();
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface Highlight {
+  id: number;
+  attribute: string;
+  start: number;
+  length: number;
+}
+
+export const Highlight = {
+  create(fields: Partial<Highlight> = {}): Highlight {
+    return {
+      id: 0,
+      attribute: "",
+      start: 0,
+      length: 0,
+      ...fields,
+    };
+  },
+
+  encode(message: Highlight, writer: Writer = new Writer()): Writer {
+    if (message.id !== 0) {
+      writer.tag(1, WireType.Varint).uint32(message.id);
+    }
+    if (message.attribute !== "") {
+      writer.tag(2, WireType.LengthDelimited).string(message.attribute);
+    }
+    if (message.start !== 0) {
+      writer.tag(3, WireType.Varint).uint32(message.start);
+    }
+    if (message.length !== 0) {
+      writer.tag(4, WireType.Varint).uint32(message.length);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): Highlight {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = Highlight.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.id = reader.uint32();
+          break;
+        }
+        case 2: {
+          message.attribute = reader.string();
+          break;
+        }
+        case 3: {
+          message.start = reader.uint32();
+          break;
+        }
+        case 4: {
+          message.length = reader.uint32();
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface SearchResponse {
+  token: string;
+  ids: number[];
+  total: number;
+  locations: { [key: string]: number };
+  meals: { [key: string]: number };
+  tags: { [key: string]: number };
+  highlights: Highlight[];
+}
+
+export const SearchResponse = {
+  create(fields: Partial<SearchResponse> = {}): SearchResponse {
+    return {
+      token: "",
+      ids: [],
+      total: 0,
+      locations: {},
+      meals: {},
+      tags: {},
+      highlights: [],
+      ...fields,
+    };
+  },
+
+  encode(message: SearchResponse, writer: Writer = new Writer()): Writer {
+    if (message.token !== "") {
+      writer.tag(1, WireType.LengthDelimited).string(message.token);
+    }
+    if (message.ids.length !== 0) {
+      writer.tag(2, WireType.LengthDelimited).fork();
+      for (const value of message.ids) {
+        writer.uint32(value);
+      }
+      writer.ldelim();
+    }
+    if (message.total !== 0) {
+      writer.tag(3, WireType.Varint).uint32(message.total);
+    }
+    for (const [key, value] of Object.entries(message.locations)) {
+      writer.tag(4, WireType.LengthDelimited).fork();
+      if (key !== "") {
+        writer.tag(1, WireType.LengthDelimited).string(key);
+      }
+      if (value !== 0) {
+        writer.tag(2, WireType.Varint).uint32(value);
+      }
+      writer.ldelim();
+    }
+    for (const [key, value] of Object.entries(message.meals)) {
+      writer.tag(5, WireType.LengthDelimited).fork();
+      if (key !== "") {
+        writer.tag(1, WireType.LengthDelimited).string(key);
+      }
+      if (value !== 0) {
+        writer.tag(2, WireType.Varint).uint32(value);
+      }
+      writer.ldelim();
+    }
+    for (const [key, value] of Object.entries(message.tags)) {
+      writer.tag(6, WireType.LengthDelimited).fork();
+      if (key !== "") {
+        writer.tag(1, WireType.LengthDelimited).string(key);
+      }
+      if (value !== 0) {
+        writer.tag(2, WireType.Varint).uint32(value);
+      }
+      writer.ldelim();
+    }
+    for (const value of message.highlights) {
+      Highlight.encode(value, writer.tag(7, WireType.LengthDelimited).fork()).ldelim();
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): SearchResponse {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = SearchResponse.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.token = reader.string();
+          break;
+        }
+        case 2: {
+          if (wireType === WireType.LengthDelimited) {
+            const packed = reader.end(reader.uint32());
+            while (reader.pos < packed) {
+              message.ids.push(reader.uint32());
+            }
+          } else {
+            message.ids.push(reader.uint32());
+          }
+          break;
+        }
+        case 3: {
+          message.total = reader.uint32();
+          break;
+        }
+        case 4: {
+          const entry = reader.end(reader.uint32());
+          let key = "";
+          let value = 0;
+          while (reader.pos < entry) {
+            const [entryField, entryWireType] = reader.tag();
+            if (entryField === 1) {
+              key = reader.string();
+            } else if (entryField === 2) {
+              value = reader.uint32();
+            } else {
+              reader.skip(entryWireType);
+            }
+          }
+          message.locations[key] = value;
+          break;
+        }
+        case 5: {
+          const entry = reader.end(reader.uint32());
+          let key = "";
+          let value = 0;
+          while (reader.pos < entry) {
+            const [entryField, entryWireType] = reader.tag();
+            if (entryField === 1) {
+              key = reader.string();
+            } else if (entryField === 2) {
+              value = reader.uint32();
+            } else {
+              reader.skip(entryWireType);
+            }
+          }
+          message.meals[key] = value;
+          break;
+        }
+        case 6: {
+          const entry = reader.end(reader.uint32());
+          let key = "";
+          let value = 0;
+          while (reader.pos < entry) {
+            const [entryField, entryWireType] = reader.tag();
+            if (entryField === 1) {
+              key = reader.string();
+            } else if (entryField === 2) {
+              value = reader.uint32();
+            } else {
+              reader.skip(entryWireType);
+            }
+          }
+          message.tags[key] = value;
+          break;
+        }
+        case 7: {
+          message.highlights.push(Highlight.decode(reader, reader.uint32()));
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
+
+export interface Error {
+  code: string;
+  message: string;
+}
+
+export const Error = {
+  create(fields: Partial<Error> = {}): Error {
+    return {
+      code: "",
+      message: "",
+      ...fields,
+    };
+  },
+
+  encode(message: Error, writer: Writer = new Writer()): Writer {
+    if (message.code !== "") {
+      writer.tag(1, WireType.LengthDelimited).string(message.code);
+    }
+    if (message.message !== "") {
+      writer.tag(2, WireType.LengthDelimited).string(message.message);
+    }
+    return writer;
+  },
+
+  decode(input: Uint8Array | Reader, length?: number): Error {
+    const reader = Reader.from(input);
+    const end = reader.end(length);
+    const message = Error.create();
+    while (reader.pos < end) {
+      const [field, wireType] = reader.tag();
+      switch (field) {
+        case 1: {
+          message.code = reader.string();
+          break;
+        }
+        case 2: {
+          message.message = reader.string();
+          break;
+        }
+        default:
+          reader.skip(wireType);
+      }
+    }
+    return message;
+  },
+};
@@ -0,0 +1,203 @@
+// Generated by `cargo codegen` from server::manifest, do not edit.
+
+export type Body = { message: string } | { json: string } | "text";
+
+export interface Route {
+  method: "GET" | "POST";
+  path: string;
+  auth: "token" | "admin" | "none";
+  query: string[];
+  request: Body | null;
+  response: Body;
+  summary: string;
+}
+
+export const routes: Route[] = [
+  {
+    "method": "POST",
+    "path": "/votes",
+    "auth": "token",
+    "query": [],
+    "request": {
+      "message": "payloads.Votes"
+    },
+    "response": {
+      "message": "payloads.VotesAccepted"
+    },
+    "summary": "Applies the flipped bits between two vote bitmaps"
+  },
+  {
+    "method": "GET",
+    "path": "/search",
+    "auth": "token",
+    "query": [],
+    "request": {
+      "message": "payloads.SearchRequest"
+    },
+    "response": {
+      "message": "payloads.SearchResponse"
+    },
+    "summary": "Searches the food index, with filters, sort, paging, highlights and facet counts"
+  },
+  {
+    "method": "GET",
+    "path": "/leaderboard",
+    "auth": "token",
+    "query": [
+      "location",
+      "limit",
+      "window"
+    ],
+    "request": null,
+    "response": {
+      "message": "payloads.Leaderboard"
+    },
+    "summary": "Most voted foods, optionally of one location or the last window hours"
+  },
+  {
+    "method": "GET",
+    "path": "/bank",
+    "auth": "token",
+    "query": [],
+    "request": null,
+    "response": {
+      "message": "foods.Bank"
+    },
+    "summary": "Current food bank"
+  },
+  {
+    "method": "GET",
+    "path": "/metrics",
+    "auth": "none",
+    "query": [],
+    "request": null,
+    "response": "text",
+    "summary": "Prometheus metrics"
+  },
+  {
+    "method": "GET",
+    "path": "/healthz",
+    "auth": "none",
+    "query": [],
+    "request": null,
+    "response": "text",
+    "summary": "Liveness"
+  },
+  {
+    "method": "GET",
+    "path": "/readyz",
+    "auth": "none",
+    "query": [],
+    "request": null,
+    "response": {
+      "json": "Readiness"
+    },
+    "summary": "Readiness of Redis and Meilisearch"
+  },
+  {
+    "method": "GET",
+    "path": "/admin/jobs",
+    "auth": "admin",
+    "query": [],
+    "request": null,
+    "response": {
+      "json": "JobStatus[]"
+    },
+    "summary": "Status of every cron job"
+  },
+  {
+    "method": "POST",
+    "path": "/admin/jobs/{name}/run",
+    "auth": "admin",
+    "query": [],
+    "request": null,
+    "response": {
+      "json": "JobStatus"
+    },
+    "summary": "Runs a job now"
+  },
+  {
+    "method": "GET",
+    "path": "/admin/foods/{key}",
+    "auth": "admin",
+    "query": [],
+    "request": null,
+    "response": {
+      "json": "FoodReport"
+    },
+    "summary": "Bank entry, votes and search document of a food id or name"
+  },
+  {
+    "method": "POST",
+    "path": "/admin/foods/{key}/votes",
+    "auth": "admin",
+    "query": [],
+    "request": {
+      "json": "VoteChange"
+    },
+    "response": {
+      "json": "VotesChanged"
+    },
+    "summary": "Sets or adjusts the votes of a food"
+  },
+  {
+    "method": "GET",
+    "path": "/admin/foods/{key}/history",
+    "auth": "admin",
+    "query": [
+      "bucket",
+      "count"
+    ],
+    "request": null,
+    "response": {
+      "json": "VoteHistory"
+    },
+    "summary": "Vote delta of a food per hour or day, oldest first"
+  },
+  {
+    "method": "GET",
+    "path": "/admin/users/{user}",
+    "auth": "admin",
+    "query": [],
+    "request": null,
+    "response": {
+      "json": "UserReport"
+    },
+    "summary": "Latest vote bitmap of a user"
+  },
+  {
+    "method": "POST",
+    "path": "/admin/bank/refresh",
+    "auth": "admin",
+    "query": [],
+    "request": null,
+    "response": {
+      "json": "JobStatus"
+    },
+    "summary": "Runs the bank_refresh job now"
+  },
+  {
+    "method": "POST",
+    "path": "/admin/index/rebuild",
+    "auth": "admin",
+    "query": [],
+    "request": null,
+    "response": {
+      "json": "number"
+    },
+    "summary": "Rebuilds the search index"
+  },
+  {
+    "method": "GET",
+    "path": "/admin/audit",
+    "auth": "admin",
+    "query": [
+      "limit"
+    ],
+    "request": null,
+    "response": {
+      "json": "AuditEntry[]"
+    },
+    "summary": "Latest admin changes, newest first"
+  }
+];